@@ -0,0 +1,189 @@
+//! Create fully configured keys without exposing them half-configured
+//!
+use crate::utils::String;
+use crate::{
+    DropAction, Key, KeyError, KeyPermissions, KeyRing, KeyRingIdentifier, KeyType, OwnedKey,
+    OwnedKeyRing,
+};
+use alloc::string::ToString;
+
+/// Description of the private keyring keys are staged in
+const STAGING_DESCRIPTION: &str = "_linux_keyutils_staging";
+
+/// Create a key with its type, permissions, timeout and ownership applied
+/// before it becomes visible in the destination keyring.
+///
+/// Adding a key with [KeyRing::add_key] and then configuring it leaves a window
+/// where the key is linked into a possibly shared keyring with the default
+/// permissions. Instead the builder creates the key in a private staging
+/// keyring under the thread keyring, applies the configuration and only then
+/// moves it into the destination with [KeyRing::move_key]. Should any step
+/// fail the staged key is invalidated.
+///
+/// Since the key is moved after its permissions are applied, those permissions
+/// must grant the possessor search and link permission, and the destination
+/// keyring must grant the caller write permission. Should the destination
+/// already contain a key of the same type and description, that link is
+/// displaced.
+///
+/// Usage:
+///
+/// ```
+/// use linux_keyutils::{KeyBuilder, KeyError, KeyPermissionsBuilder, KeyRing};
+/// use linux_keyutils::{KeyRingIdentifier, KeyType, Permission};
+///
+/// fn store_secret(secret: &[u8]) -> Result<(), KeyError> {
+///     let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false)?;
+///
+///     // Only the possessor may access the key, and it expires after an hour
+///     let perms = KeyPermissionsBuilder::builder()
+///         .posessor(Permission::ALL)
+///         .build();
+///     let key = KeyBuilder::builder("my-locked-key")
+///         .key_type(KeyType::Logon)
+///         .perms(perms)
+///         .timeout(3600)
+///         .build(ring, secret)?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyBuilder {
+    ktype: KeyType,
+    description: String,
+    perms: Option<KeyPermissions>,
+    timeout: Option<usize>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+}
+
+impl KeyBuilder {
+    /// Start a KeyBuilder for a [KeyType::User] key with the provided description
+    pub fn builder<D: AsRef<str> + ?Sized>(description: &D) -> Self {
+        Self {
+            ktype: KeyType::User,
+            description: description.as_ref().to_string(),
+            perms: None,
+            timeout: None,
+            uid: None,
+            gid: None,
+        }
+    }
+
+    /// Set the type of the key
+    pub fn key_type(mut self, ktype: KeyType) -> Self {
+        self.ktype = ktype;
+        self
+    }
+
+    /// Set the permissions of the key, see [Key::set_perms]
+    pub fn perms(mut self, perms: KeyPermissions) -> Self {
+        self.perms = Some(perms);
+        self
+    }
+
+    /// Set a timeout in seconds after which the key expires, see [Key::set_timeout]
+    pub fn timeout(mut self, seconds: usize) -> Self {
+        self.timeout = Some(seconds);
+        self
+    }
+
+    /// Change the owning UID and/or GID of the key, see [Key::chown]
+    pub fn chown(mut self, uid: Option<u32>, gid: Option<u32>) -> Self {
+        self.uid = uid;
+        self.gid = gid;
+        self
+    }
+
+    /// Create the key with the provided payload and link it into the
+    /// destination keyring, returning the configured key.
+    pub fn build<S: AsRef<[u8]> + ?Sized>(
+        &self,
+        destination: KeyRing,
+        payload: &S,
+    ) -> Result<Key, KeyError> {
+        // The staging keyring is only reachable from the calling thread
+        let thread = KeyRing::from_special_id(KeyRingIdentifier::Thread, true)?;
+        let staging = OwnedKeyRing::new(
+            thread.add_keyring(STAGING_DESCRIPTION)?,
+            DropAction::Invalidate,
+        );
+
+        // Until moved the key is invalidated on failure
+        let key = OwnedKey::new(
+            staging.add_key_with_type(self.ktype.clone(), &self.description, payload)?,
+            DropAction::Invalidate,
+        );
+
+        // Permissions are applied last, as they may revoke setattr permission
+        if let Some(seconds) = self.timeout {
+            key.set_timeout(seconds)?;
+        }
+        if self.uid.is_some() || self.gid.is_some() {
+            key.chown(self.uid, self.gid)?;
+        }
+        if let Some(perms) = self.perms {
+            key.set_perms(perms)?;
+        }
+
+        staging.move_key(*key, destination, false)?;
+        Ok(key.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyPermissionsBuilder, Permission};
+
+    #[test]
+    fn test_build_key() {
+        KeyRing::with_new_session(|ring| {
+            let perms = KeyPermissionsBuilder::builder()
+                .posessor(
+                    Permission::VIEW | Permission::READ | Permission::SEARCH | Permission::LINK,
+                )
+                .user(Permission::VIEW)
+                .build();
+            let key = KeyBuilder::builder("test_build_key")
+                .perms(perms)
+                .timeout(300)
+                .chown(None, Some(unsafe { libc::getegid() }))
+                .build(ring, b"secret")
+                .unwrap();
+
+            // The key is configured and linked into the destination
+            let info = key.metadata().unwrap();
+            assert_eq!(info.get_type(), KeyType::User);
+            assert_eq!(info.get_description(), "test_build_key");
+            assert_eq!(info.get_perms().bits(), perms.bits());
+            assert_eq!(key.read_to_vec().unwrap(), b"secret");
+            assert!(ring.get_links().unwrap().contains(&key));
+
+            // The staging keyring is invalidated
+            let thread = KeyRing::from_special_id(KeyRingIdentifier::Thread, false).unwrap();
+            let staging = thread.search_keyring(STAGING_DESCRIPTION);
+            assert_eq!(staging.unwrap_err(), KeyError::KeyDoesNotExist);
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_build_key_without_link() {
+        KeyRing::with_new_session(|ring| {
+            // Without link permission the key cannot be moved
+            let perms = KeyPermissionsBuilder::builder()
+                .posessor(Permission::VIEW | Permission::READ | Permission::SEARCH)
+                .build();
+            let result = KeyBuilder::builder("test_build_key_without_link")
+                .perms(perms)
+                .build(ring, b"secret");
+            assert_eq!(result.unwrap_err(), KeyError::AccessDenied);
+
+            // And is never exposed in the destination
+            let result = ring.search("test_build_key_without_link");
+            assert_eq!(result.unwrap_err(), KeyError::KeyDoesNotExist);
+        })
+        .unwrap();
+    }
+}
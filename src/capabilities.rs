@@ -0,0 +1,110 @@
+//! Feature detection of the running kernel's key-management facility
+//!
+use crate::ffi::{self, KeyCtlOperation};
+use crate::KeyError;
+use bitflags::bitflags;
+
+bitflags! {
+    /// Features supported by the running kernel, as reported by
+    /// KEYCTL_CAPABILITIES. See `man 3 keyctl_capabilities`.
+    ///
+    /// The kernel reports the capabilities as a byte array, the first byte
+    /// makes up the low 8 bits of the flags and the second byte the high 8 bits.
+    ///
+    /// Usage:
+    ///
+    /// ```
+    /// use linux_keyutils::{Capabilities, KeyError};
+    ///
+    /// fn main() -> Result<(), KeyError> {
+    ///     let caps = Capabilities::probe()?;
+    ///     if !caps.contains(Capabilities::MOVE) {
+    ///         // Fall back to link + unlink
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    #[repr(transparent)]
+    pub struct Capabilities: u16 {
+        /// KEYCTL_CAPABILITIES is supported
+        const CAPABILITIES = 0x0001;
+        /// Persistent keyrings are supported, see [crate::KeyRing::get_persistent]
+        const PERSISTENT_KEYRINGS = 0x0002;
+        /// Diffie-Hellman computation is supported, see [crate::Key::dh_compute]
+        const DIFFIE_HELLMAN = 0x0004;
+        /// Public key operations are supported, see [crate::Key::pkey_query]
+        const PUBLIC_KEY = 0x0008;
+        /// The big_key type is supported
+        const BIG_KEY = 0x0010;
+        /// Keys can be invalidated, see [crate::Key::invalidate]
+        const INVALIDATE = 0x0020;
+        /// Keyrings can be restricted, see [crate::KeyRing::restrict]
+        const RESTRICT_KEYRING = 0x0040;
+        /// Keys can be moved between keyrings, see [crate::KeyRing::move_key]
+        const MOVE = 0x0080;
+        /// Keyring names are namespaced
+        const NS_KEYRING_NAME = 0x0100;
+        /// Keys can be tagged with namespaces
+        const NS_KEY_TAG = 0x0200;
+        /// Key change notifications are supported, see [crate::KeyWatcher]
+        const NOTIFICATIONS = 0x0400;
+    }
+}
+
+impl Capabilities {
+    /// Query the capabilities of the running kernel.
+    ///
+    /// Kernels predating KEYCTL_CAPABILITIES (added in 5.3) report no
+    /// capabilities at all, rather than an error.
+    pub fn probe() -> Result<Self, KeyError> {
+        let mut buffer = [0u8; 2];
+        let res = ffi::keyctl!(
+            KeyCtlOperation::Capabilities,
+            buffer.as_mut_ptr() as _,
+            buffer.len() as _
+        );
+        match res {
+            Ok(_) => Ok(Self::from_bytes(buffer)),
+            Err(KeyError::OperationNotSupported) => Ok(Self::empty()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Internal method to decode the byte array returned by the kernel
+    pub(crate) fn from_bytes(bytes: [u8; 2]) -> Self {
+        Self::from_bits_truncate(u16::from_le_bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bytes() {
+        let caps = Capabilities::from_bytes([0xeb, 0x03]);
+        assert!(caps.contains(Capabilities::CAPABILITIES));
+        assert!(caps.contains(Capabilities::PERSISTENT_KEYRINGS));
+        assert!(caps.contains(Capabilities::PUBLIC_KEY));
+        assert!(caps.contains(Capabilities::MOVE));
+        assert!(caps.contains(Capabilities::NS_KEY_TAG));
+        assert!(!caps.contains(Capabilities::DIFFIE_HELLMAN));
+        assert!(!caps.contains(Capabilities::BIG_KEY));
+        assert!(!caps.contains(Capabilities::NOTIFICATIONS));
+
+        // Unknown bits are dropped
+        assert_eq!(
+            Capabilities::from_bytes([0x00, 0xf8]),
+            Capabilities::empty()
+        );
+    }
+
+    #[test]
+    fn test_probe() {
+        let caps = Capabilities::probe().unwrap();
+        if !caps.is_empty() {
+            assert!(caps.contains(Capabilities::CAPABILITIES));
+        }
+    }
+}
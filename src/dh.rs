@@ -0,0 +1,49 @@
+//! Types used by the Diffie-Hellman computation of [Key](crate::Key)
+//!
+use crate::utils::{CString, String, Vec};
+use crate::KeyError;
+
+/// Key derivation applied to the result of
+/// [Key::dh_compute_kdf](crate::Key::dh_compute_kdf).
+///
+/// The kernel uses the KDF defined in SP800-56A, with the shared secret
+/// and the optional other info as input.
+///
+/// Usage:
+///
+/// ```
+/// use linux_keyutils::DhKdf;
+///
+/// let kdf = DhKdf::new("sha256").other_info(b"session-key");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DhKdf {
+    hash: String,
+    other_info: Vec<u8>,
+}
+
+impl DhKdf {
+    /// Create a new KDF using the named hash algorithm, i.e. `sha256`.
+    pub fn new<H: AsRef<str> + ?Sized>(hash: &H) -> Self {
+        Self {
+            hash: hash.as_ref().into(),
+            other_info: Vec::new(),
+        }
+    }
+
+    /// Set the other info mixed into the derivation
+    pub fn other_info<T: AsRef<[u8]> + ?Sized>(mut self, info: &T) -> Self {
+        self.other_info = info.as_ref().into();
+        self
+    }
+
+    /// Internal method to construct the hash name passed to the kernel
+    pub(crate) fn hash_cstring(&self) -> Result<CString, KeyError> {
+        CString::new(self.hash.as_str()).or(Err(KeyError::InvalidArguments))
+    }
+
+    /// Internal accessor for the other info
+    pub(crate) fn get_other_info(&self) -> &[u8] {
+        &self.other_info
+    }
+}
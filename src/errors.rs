@@ -51,6 +51,10 @@ pub enum KeyError {
     /// Operation not supported
     OperationNotSupported,
 
+    /// The keyring is already restricted, or an exclusive move found a
+    /// matching key already linked to the destination keyring
+    AlreadyExists,
+
     /// Write to destination failed
     WriteError,
 
@@ -63,6 +67,9 @@ pub enum KeyError {
     // binary. I.e. keyutils utilities are not installed.
     MissingFileOrDirectory,
 
+    /// The data being read changed size repeatedly while it was being read
+    ContentChanged,
+
     /// Unknown - catch all, return this instead of panicing
     Unknown(i32),
 }
@@ -80,7 +87,40 @@ impl Error for KeyError {}
 impl KeyError {
     /// Obtain the KeyError derived from checking errno
     pub fn from_errno() -> KeyError {
-        match unsafe { *libc::__errno_location() } {
+        Self::from_code(unsafe { *libc::__errno_location() })
+    }
+
+    /// Obtain the errno value corresponding to this KeyError
+    ///
+    /// Used to report an error to the kernel, such as when rejecting a key
+    /// with [Key::reject](crate::Key::reject).
+    pub fn as_errno(&self) -> i32 {
+        match self {
+            KeyError::AccessDenied => libc::EACCES,
+            KeyError::QuotaExceeded => libc::EDQUOT,
+            KeyError::BadAddress => libc::EFAULT,
+            KeyError::InvalidArguments => libc::EINVAL,
+            KeyError::KeyExpired => libc::EKEYEXPIRED,
+            KeyError::KeyRevoked => libc::EKEYREVOKED,
+            KeyError::KeyRejected => libc::EKEYREJECTED,
+            KeyError::KeyringDoesNotExist => libc::ENOKEY,
+            KeyError::KeyDoesNotExist => libc::ENOKEY,
+            KeyError::OutOfMemory => libc::ENOMEM,
+            KeyError::InvalidDescription => libc::EINVAL,
+            KeyError::InvalidIdentifier => libc::EINVAL,
+            KeyError::OperationNotSupported => libc::ENOTSUP,
+            KeyError::AlreadyExists => libc::EEXIST,
+            KeyError::WriteError => libc::EIO,
+            KeyError::PermissionDenied => libc::EPERM,
+            KeyError::MissingFileOrDirectory => libc::ENOENT,
+            KeyError::ContentChanged => libc::EAGAIN,
+            KeyError::Unknown(x) => *x,
+        }
+    }
+
+    /// Internal method to obtain the KeyError for an errno value
+    pub(crate) fn from_code(code: i32) -> KeyError {
+        match code {
             // Create Errors
             libc::ENOENT => KeyError::MissingFileOrDirectory,
             libc::EPERM => KeyError::PermissionDenied,
@@ -94,9 +134,41 @@ impl KeyError {
             libc::ENOMEM => KeyError::OutOfMemory,
             libc::ENOKEY => KeyError::KeyDoesNotExist,
             libc::ENOTSUP => KeyError::OperationNotSupported,
+            libc::EEXIST => KeyError::AlreadyExists,
 
             // Unknown, provide error code for debugging
             x => KeyError::Unknown(x),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errno_roundtrip() {
+        for error in [
+            KeyError::AccessDenied,
+            KeyError::QuotaExceeded,
+            KeyError::BadAddress,
+            KeyError::InvalidArguments,
+            KeyError::KeyExpired,
+            KeyError::KeyRevoked,
+            KeyError::KeyRejected,
+            KeyError::KeyDoesNotExist,
+            KeyError::OutOfMemory,
+            KeyError::OperationNotSupported,
+            KeyError::AlreadyExists,
+            KeyError::PermissionDenied,
+            KeyError::MissingFileOrDirectory,
+            KeyError::Unknown(libc::EBUSY),
+        ] {
+            assert_eq!(KeyError::from_code(error.as_errno()), error);
+        }
+
+        // Variants without a dedicated errno share the closest one
+        assert_eq!(KeyError::KeyringDoesNotExist.as_errno(), libc::ENOKEY);
+        assert_eq!(KeyError::InvalidDescription.as_errno(), libc::EINVAL);
+    }
+}
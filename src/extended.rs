@@ -0,0 +1,373 @@
+//! Additional information about nodes that is only available from
+//! `/proc/keys`, such as their expiry and usage count.
+//!
+use crate::utils::{String, Vec};
+use crate::{KeyError, KeyPermissions, KeySerialId};
+use alloc::string::ToString;
+use bitflags::bitflags;
+use core::str::{self, FromStr};
+use core::time::Duration;
+
+bitflags! {
+    /// State of a key, as reported by the flags column of `/proc/keys`.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[repr(transparent)]
+    pub struct KeyFlags: u8 {
+        /// The key has been instantiated (I)
+        const INSTANTIATED = 0x01;
+        /// The key has been revoked (R)
+        const REVOKED = 0x02;
+        /// The key is dead, its type has been unregistered (D)
+        const DEAD = 0x04;
+        /// The key contributes to the user's quota (Q)
+        const IN_QUOTA = 0x08;
+        /// The key is under construction via a callback to user space (U)
+        const USER_CONSTRUCT = 0x10;
+        /// The key is negatively instantiated (N)
+        const NEGATIVE = 0x20;
+        /// The key has been invalidated (i)
+        const INVALIDATED = 0x40;
+    }
+}
+
+/// Flag characters in the order they appear in `/proc/keys`
+const FLAG_CHARS: [(char, KeyFlags); 7] = [
+    ('I', KeyFlags::INSTANTIATED),
+    ('R', KeyFlags::REVOKED),
+    ('D', KeyFlags::DEAD),
+    ('Q', KeyFlags::IN_QUOTA),
+    ('U', KeyFlags::USER_CONSTRUCT),
+    ('N', KeyFlags::NEGATIVE),
+    ('i', KeyFlags::INVALIDATED),
+];
+
+/// When a key expires, see [Key::set_timeout](crate::Key::set_timeout).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyExpiry {
+    /// The key does not expire
+    Permanent,
+    /// The key has expired
+    Expired,
+    /// The key expires after the remaining time. The kernel rounds down
+    /// to whole seconds, minutes, hours, days or weeks, depending on the
+    /// magnitude of the remaining time.
+    Remaining(Duration),
+}
+
+/// Information about the given node/entry parsed from `/proc/keys`.
+/// Returned by [Key::extended_metadata](crate::Key::extended_metadata)
+/// or [KeyRing::extended_metadata](crate::KeyRing::extended_metadata).
+///
+/// Complements [Metadata](crate::Metadata) with the state, usage count and
+/// expiry of the node, which KEYCTL_DESCRIBE does not report.
+#[derive(Debug, Clone)]
+pub struct ExtendedMetadata {
+    id: KeySerialId,
+    flags: KeyFlags,
+    usage: u32,
+    expiry: KeyExpiry,
+    perm: KeyPermissions,
+    uid: u32,
+    gid: u32,
+    type_name: String,
+    summary: String,
+}
+
+impl FromStr for KeyExpiry {
+    type Err = KeyError;
+
+    /// Parse the timeout column, i.e. `perm`, `expd` or a number followed by
+    /// one of the units `s`, `m`, `h`, `d` or `w`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expiry = match s {
+            "perm" => KeyExpiry::Permanent,
+            "expd" => KeyExpiry::Expired,
+            _ => {
+                let split = s.len().checked_sub(1).ok_or(KeyError::InvalidDescription)?;
+                let (value, unit) = (s.get(..split), s.get(split..));
+                let value: u64 = value
+                    .and_then(|v| v.parse().ok())
+                    .ok_or(KeyError::InvalidDescription)?;
+                let scale = match unit {
+                    Some("s") => 1,
+                    Some("m") => 60,
+                    Some("h") => 60 * 60,
+                    Some("d") => 60 * 60 * 24,
+                    Some("w") => 60 * 60 * 24 * 7,
+                    _ => return Err(KeyError::InvalidDescription),
+                };
+                let secs = value
+                    .checked_mul(scale)
+                    .ok_or(KeyError::InvalidDescription)?;
+                KeyExpiry::Remaining(Duration::from_secs(secs))
+            }
+        };
+        Ok(expiry)
+    }
+}
+
+impl FromStr for ExtendedMetadata {
+    type Err = KeyError;
+
+    /// Each line of `/proc/keys` contains the following information about
+    /// a key:
+    ///
+    /// `id flags usage timeout perm uid gid type summary`
+    ///
+    /// In the above, id and perm are hexadecimal, usage, uid and gid are
+    /// decimal, and the type is truncated to 9 characters. The summary is
+    /// the description of the key, followed by information specific to its
+    /// type such as the payload length.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rest = s;
+        let mut next = || -> Result<&str, KeyError> {
+            let trimmed = rest.trim_start_matches(' ');
+            let end = trimmed.find(' ').ok_or(KeyError::InvalidDescription)?;
+            rest = &trimmed[end..];
+            Ok(&trimmed[..end])
+        };
+
+        // Parse the ID
+        let id = u32::from_str_radix(next()?, 16)
+            .map(|raw| KeySerialId::new(raw as i32))
+            .or(Err(KeyError::InvalidDescription))?;
+
+        // Parse the flags, each is either set or '-'
+        let column = next()?;
+        if column.chars().count() != FLAG_CHARS.len() {
+            return Err(KeyError::InvalidDescription);
+        }
+        let mut flags = KeyFlags::empty();
+        for (c, (expected, flag)) in column.chars().zip(FLAG_CHARS) {
+            match c {
+                '-' => {}
+                c if c == expected => flags |= flag,
+                _ => return Err(KeyError::InvalidDescription),
+            }
+        }
+
+        // Parse the usage count and timeout
+        let usage = next()?.parse().or(Err(KeyError::InvalidDescription))?;
+        let expiry = next()?.parse()?;
+
+        // Parse the permissions and ownership
+        let perm = u32::from_str_radix(next()?, 16).or(Err(KeyError::InvalidDescription))?;
+        let uid = next()?.parse().or(Err(KeyError::InvalidDescription))?;
+        let gid = next()?.parse().or(Err(KeyError::InvalidDescription))?;
+
+        // The type is padded to 9 characters followed by a space, the
+        // summary itself may start with or contain spaces
+        let column = rest.trim_start_matches(' ');
+        let end = column.find(' ').unwrap_or(column.len());
+        let type_name = column[..end].to_string();
+        let summary = column
+            .get(type_name.len().max(9) + 1..)
+            .unwrap_or("")
+            .to_string();
+
+        Ok(Self {
+            id,
+            flags,
+            usage,
+            expiry,
+            perm: KeyPermissions::from_u32(perm),
+            uid,
+            gid,
+            type_name,
+            summary,
+        })
+    }
+}
+
+impl ExtendedMetadata {
+    /// Internal method to locate the entry of a node in `/proc/keys`.
+    ///
+    /// Only nodes that grant the caller view permission are listed, others
+    /// fail with [KeyError::KeyDoesNotExist].
+    pub(crate) fn from_id(id: KeySerialId) -> Result<Self, KeyError> {
+        let contents = read_proc_keys()?;
+        let contents = str::from_utf8(&contents).or(Err(KeyError::InvalidDescription))?;
+
+        // Lines start with the zero padded hexadecimal ID
+        let prefix = alloc::format!("{:08x} ", id.as_raw_id() as u32);
+        contents
+            .lines()
+            .find(|line| line.starts_with(&prefix))
+            .ok_or(KeyError::KeyDoesNotExist)?
+            .parse()
+    }
+
+    /// The ID of this entry
+    pub fn get_id(&self) -> KeySerialId {
+        self.id
+    }
+
+    /// The current state of this entry
+    pub fn get_flags(&self) -> KeyFlags {
+        self.flags
+    }
+
+    /// The number of references held on this entry
+    pub fn get_usage(&self) -> u32 {
+        self.usage
+    }
+
+    /// When this entry expires
+    pub fn get_expiry(&self) -> KeyExpiry {
+        self.expiry
+    }
+
+    /// The current permissions of this entry
+    pub fn get_perms(&self) -> KeyPermissions {
+        self.perm
+    }
+
+    /// The owning UID of this entry
+    pub fn get_uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// The owning GID of this entry
+    pub fn get_gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// The name of the type of this entry, truncated to 9 characters
+    pub fn get_type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// The description of this entry, followed by type specific
+    /// information such as the length of the payload
+    pub fn get_summary(&self) -> &str {
+        &self.summary
+    }
+}
+
+/// Internal method to read the complete contents of `/proc/keys`
+fn read_proc_keys() -> Result<Vec<u8>, KeyError> {
+    let fd = unsafe { libc::open(c"/proc/keys".as_ptr(), libc::O_RDONLY | libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(KeyError::from_errno());
+    }
+
+    // The file is generated while it is read, so read until EOF
+    let mut contents = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let result = loop {
+        let res = unsafe { libc::read(fd, chunk.as_mut_ptr() as _, chunk.len()) };
+        match res {
+            0 => break Ok(contents),
+            x if x > 0 => contents.extend_from_slice(&chunk[..x as usize]),
+            _ => match KeyError::from_errno() {
+                KeyError::Unknown(libc::EINTR) => continue,
+                err => break Err(err),
+            },
+        }
+    };
+    unsafe { libc::close(fd) };
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyRing;
+
+    #[test]
+    fn test_parse_line() {
+        let line = "0836a4aa I--Q---     2 perm 1f3f0000     0 65534 keyring   _uid.0: 1";
+        let info = ExtendedMetadata::from_str(line).unwrap();
+        assert_eq!(info.get_id(), KeySerialId::new(0x0836a4aa));
+        assert_eq!(
+            info.get_flags(),
+            KeyFlags::INSTANTIATED | KeyFlags::IN_QUOTA
+        );
+        assert_eq!(info.get_usage(), 2);
+        assert_eq!(info.get_expiry(), KeyExpiry::Permanent);
+        assert_eq!(info.get_perms().bits(), 0x1f3f0000);
+        assert_eq!(info.get_uid(), 0);
+        assert_eq!(info.get_gid(), 65534);
+        assert_eq!(info.get_type_name(), "keyring");
+        assert_eq!(info.get_summary(), "_uid.0: 1");
+
+        // Truncated type names and summaries with spaces
+        let line = "1a2b3c4d IR-Q--i     1  expd 3f010000  1000  1000 asymmetri  my key : X509.rsa";
+        let info = ExtendedMetadata::from_str(line).unwrap();
+        let flags =
+            KeyFlags::INSTANTIATED | KeyFlags::REVOKED | KeyFlags::IN_QUOTA | KeyFlags::INVALIDATED;
+        assert_eq!(info.get_flags(), flags);
+        assert_eq!(info.get_expiry(), KeyExpiry::Expired);
+        assert_eq!(info.get_type_name(), "asymmetri");
+        assert_eq!(info.get_summary(), " my key : X509.rsa");
+
+        // Malformed lines are rejected
+        for line in [
+            "",
+            "zzzzzzzz I--Q---     2 perm 1f3f0000     0 65534 keyring   _uid.0: 1",
+            "0836a4aa I--X---     2 perm 1f3f0000     0 65534 keyring   _uid.0: 1",
+            "0836a4aa I--Q---     2 10y 1f3f0000     0 65534 keyring   _uid.0: 1",
+            "0836a4aa I--Q---     2 perm",
+        ] {
+            let result = ExtendedMetadata::from_str(line);
+            assert_eq!(result.unwrap_err(), KeyError::InvalidDescription);
+        }
+    }
+
+    #[test]
+    fn test_parse_expiry() {
+        for (column, secs) in [
+            ("59s", 59),
+            ("4m", 4 * 60),
+            ("23h", 23 * 3600),
+            ("6d", 6 * 86400),
+            ("2w", 2 * 604800),
+        ] {
+            let expiry = KeyExpiry::from_str(column).unwrap();
+            assert_eq!(expiry, KeyExpiry::Remaining(Duration::from_secs(secs)));
+        }
+        assert_eq!(KeyExpiry::from_str("perm").unwrap(), KeyExpiry::Permanent);
+        assert_eq!(KeyExpiry::from_str("expd").unwrap(), KeyExpiry::Expired);
+        assert!(KeyExpiry::from_str("").is_err());
+        assert!(KeyExpiry::from_str("m").is_err());
+        assert!(KeyExpiry::from_str("5y").is_err());
+        assert!(KeyExpiry::from_str("100000000000000w").is_err());
+    }
+
+    #[test]
+    fn test_extended_metadata() {
+        KeyRing::with_new_session(|ring| {
+            let key = ring.add_key("my extended key", b"Test Data").unwrap();
+
+            // A fresh key never expires
+            let info = key.extended_metadata().unwrap();
+            assert_eq!(info.get_id(), key.get_id());
+            assert!(info.get_flags().contains(KeyFlags::INSTANTIATED));
+            assert!(info.get_usage() >= 1);
+            assert_eq!(info.get_expiry(), KeyExpiry::Permanent);
+            assert_eq!(info.get_perms().bits(), 0x3f010000);
+            assert_eq!(info.get_uid(), unsafe { libc::geteuid() });
+            assert_eq!(info.get_type_name(), "user");
+            assert_eq!(info.get_summary(), "my extended key: 9");
+
+            // Timeouts are reported in whole minutes
+            key.set_timeout(300).unwrap();
+            let expiry = key.extended_metadata().unwrap().get_expiry();
+            let valid = [Duration::from_secs(240), Duration::from_secs(300)];
+            assert!(matches!(expiry, KeyExpiry::Remaining(d) if valid.contains(&d)));
+
+            // Keyrings are listed as well
+            let info = ring.extended_metadata().unwrap();
+            assert_eq!(info.get_type_name(), "keyring");
+            assert_eq!(info.get_summary(), "_ses: 1");
+
+            // Invalidated keys are flagged until garbage collected
+            key.invalidate().unwrap();
+            match key.extended_metadata() {
+                Ok(info) => assert!(info.get_flags().contains(KeyFlags::INVALIDATED)),
+                Err(e) => assert_eq!(e, KeyError::KeyDoesNotExist),
+            }
+        })
+        .unwrap();
+    }
+}
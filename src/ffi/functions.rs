@@ -1,9 +1,8 @@
 //! Raw System Call Wrappers
 //!
 use super::types::{KeyCtlOperation, KeySerialId, KeyType};
+use crate::utils::{CString, Vec};
 use crate::KeyError;
-use alloc::ffi::CString;
-use core::ffi::CStr;
 
 /// add_key() creates or updates a key of the given type and description, instantiates
 /// it with the payload of length plen, attaches it to the nominated keyring, and
@@ -19,7 +18,7 @@ use core::ffi::CStr;
 /// caller has write  permission.  Alternatively, it may be one of the following special
 /// keyring IDs:
 pub(crate) fn add_key(
-    ktype: KeyType,
+    ktype: &KeyType,
     keyring: libc::c_ulong,
     description: &str,
     payload: Option<&[u8]>,
@@ -37,7 +36,7 @@ pub(crate) fn add_key(
     let res = unsafe {
         libc::syscall(
             libc::SYS_add_key,
-            Into::<&'static CStr>::into(ktype).as_ptr(),
+            ktype.as_cstr().as_ptr(),
             description.as_ptr(),
             payload,
             plen as libc::size_t,
@@ -83,7 +82,7 @@ pub(crate) fn add_key(
 /// If the key is not found and callout info is not empty, then the kernel attempts
 /// to invoke a user-space program to instantiate the key.
 pub(crate) fn request_key(
-    ktype: KeyType,
+    ktype: &KeyType,
     keyring: libc::c_ulong,
     description: &str,
     info: Option<&str>,
@@ -97,7 +96,7 @@ pub(crate) fn request_key(
     let res = unsafe {
         libc::syscall(
             libc::SYS_request_key,
-            Into::<&'static CStr>::into(ktype).as_ptr(),
+            ktype.as_cstr().as_ptr(),
             description.as_ptr(),
             info.map_or_else(core::ptr::null, |_| callout.as_ptr()),
             keyring as u32,
@@ -138,3 +137,83 @@ pub(crate) fn keyctl_impl(
     // Otherwise return the result
     Ok(res)
 }
+
+/// Maximum number of times [keyctl_read_alloc] will grow its buffer and retry
+/// before concluding that the data is changing underneath it.
+const MAX_READ_ATTEMPTS: usize = 8;
+
+/// Buffers that [keyctl_read_alloc] can allocate and read into.
+pub(crate) trait ReadBuffer: AsMut<[u8]> + Sized {
+    /// Allocate a zeroed buffer of the provided length
+    fn allocate(len: usize) -> Result<Self, KeyError>;
+
+    /// Shorten the buffer to the length of the data read
+    fn truncate(&mut self, len: usize);
+
+    /// Dispose of a buffer that was too small, and may hold partial data
+    fn discard(self) {}
+}
+
+impl ReadBuffer for Vec<u8> {
+    fn allocate(len: usize) -> Result<Self, KeyError> {
+        Ok(alloc::vec![0u8; len])
+    }
+
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len)
+    }
+
+    #[cfg(feature = "zeroize")]
+    fn discard(mut self) {
+        zeroize::Zeroize::zeroize(&mut self);
+    }
+}
+
+/// Perform a keyctl() operation that copies a variable amount of data into a
+/// user-space buffer, returning a newly allocated buffer holding all of it.
+///
+/// Operations such as KEYCTL_READ, KEYCTL_DESCRIBE and KEYCTL_GET_SECURITY
+/// always return the full size of the available data, regardless of the size
+/// of the buffer provided. This is used to first probe the required length,
+/// and then grow and retry should the data have grown in between calls.
+///
+/// If the data keeps changing size, [KeyError::ContentChanged] is returned.
+pub(crate) fn keyctl_read_alloc<B: ReadBuffer>(
+    operation: KeyCtlOperation,
+    id: KeySerialId,
+) -> Result<B, KeyError> {
+    // Probe the current length of the data
+    let mut len = keyctl_impl(
+        operation,
+        id.as_raw_id() as libc::c_ulong,
+        core::ptr::null_mut::<u8>() as _,
+        0,
+        0,
+    )? as usize;
+
+    for _ in 0..MAX_READ_ATTEMPTS {
+        let mut buffer = B::allocate(len)?;
+
+        // Perform the read, obtaining the size of the data at this point in time
+        let required = keyctl_impl(
+            operation,
+            id.as_raw_id() as libc::c_ulong,
+            buffer.as_mut().as_mut_ptr() as _,
+            buffer.as_mut().len() as _,
+            0,
+        )? as usize;
+
+        // The complete data fit in the buffer
+        if required <= len {
+            buffer.truncate(required);
+            return Ok(buffer);
+        }
+
+        // Otherwise the data grew, the partial data read so far is discarded
+        buffer.discard();
+
+        // Retry with the new size
+        len = required;
+    }
+    Err(KeyError::ContentChanged)
+}
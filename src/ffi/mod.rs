@@ -15,13 +15,16 @@ macro_rules! keyctl {
     ( $op:expr, $a2:expr ) => {
         $crate::ffi::keyctl_impl($op, $a2, 0, 0, 0)
     };
+    ( $op:expr ) => {
+        $crate::ffi::keyctl_impl($op, 0, 0, 0, 0)
+    };
 }
 
 #[allow(unused_imports)]
 pub use types::*;
 
 #[allow(unused_imports)]
-pub(crate) use functions::{add_key, keyctl_impl, request_key};
+pub(crate) use functions::{add_key, keyctl_impl, keyctl_read_alloc, request_key, ReadBuffer};
 
 // Export the macro for use
 pub(crate) use keyctl;
@@ -1,6 +1,6 @@
 //! Definitions ported from the C keyutils library
 //!
-use crate::utils::CStr;
+use crate::utils::{CStr, CString};
 use crate::KeyError;
 
 /// Primary kernel identifier for a key or keyring.
@@ -8,8 +8,11 @@ use crate::KeyError;
 #[repr(transparent)]
 pub struct KeySerialId(pub i32);
 
-/// Pre-defined key types the kernel understands. See `man 7 keyrings`.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Key types the kernel understands. See `man 7 keyrings`.
+///
+/// Types provided by kernel modules that are not listed here can
+/// be referred to with [KeyType::Other].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum KeyType {
     /// Keyrings  are  special  key  types that may contain links to sequences of other
     /// keys of any type.
@@ -26,6 +29,36 @@ pub enum KeyType {
     /// If the key payload is large  enough, then it may be stored encrypted in
     /// tmpfs (which can be swapped out) rather than kernel memory.
     BigKey,
+    /// Public/private keys and X.509 certificates used by the kernel crypto layer
+    /// for signature verification and the public key operations.
+    Asymmetric,
+    /// Symmetric keys generated by the kernel and encrypted/decrypted using a
+    /// trusted or user master key. User space only ever sees the encrypted blob.
+    Encrypted,
+    /// Symmetric keys generated and sealed by a hardware trust source such as
+    /// a TPM. User space only ever sees the sealed blob.
+    Trusted,
+    /// Results of DNS lookups performed on behalf of kernel network filesystems.
+    DnsResolver,
+    /// The authorization key handed to a request-key(8) helper while it is
+    /// instantiating a key.
+    RequestKeyAuth,
+    /// Hashes of keys or binaries that the kernel must refuse to trust.
+    Blacklist,
+    /// ID mappings used by the NFSv4 idmapper.
+    IdResolver,
+    /// Authentication tickets used by the Ceph filesystem.
+    Ceph,
+    /// Client security tokens used by AF_RXRPC.
+    RxRpc,
+    /// Server keys used by AF_RXRPC.
+    RxRpcServer,
+    /// ID mappings used by the CIFS client.
+    CifsIdmap,
+    /// SPNEGO blobs used by the CIFS client.
+    CifsSpnego,
+    /// Any other key type registered with the kernel, by name.
+    Other(CString),
 }
 
 /// Special identifiers for default keyrings. See `man 7 keyrings`.
@@ -48,19 +81,57 @@ pub enum KeyRingIdentifier {
     ReqKeyAuthKey = -7,
 }
 
-#[allow(dead_code)]
+/// Destination keyring for keys constructed on behalf of the calling thread
+/// when request_key(2) is called with a destination keyring of 0, or by the
+/// kernel itself. See `man 3 keyctl_set_reqkey_keyring`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DefaultKeyring {
+    /// Leave the setting unchanged, only query the current setting
     NoChange = -1,
+    /// The thread keyring if it exists, otherwise the process keyring,
+    /// the session keyring and the user-session keyring in that order
     Default = 0,
+    /// The thread keyring
     Thread = 1,
+    /// The process keyring
     Process = 2,
+    /// The session keyring
     Session = 3,
+    /// The UID-specific keyring
     User = 4,
+    /// The UID-session keyring
     UserSession = 5,
+    /// The GID-specific keyring, not currently supported by the kernel
     Group = 6,
+    /// The keyring of the process that requested the key, when called from
+    /// a request-key helper
+    RequestorKeyring = 7,
+}
+
+impl TryFrom<i64> for DefaultKeyring {
+    type Error = KeyError;
+
+    /// Convert the setting returned by the kernel
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        use DefaultKeyring::*;
+        let keyring = match value {
+            -1 => NoChange,
+            0 => Default,
+            1 => Thread,
+            2 => Process,
+            3 => Session,
+            4 => User,
+            5 => UserSession,
+            6 => Group,
+            7 => RequestorKeyring,
+            _ => return Err(KeyError::InvalidIdentifier),
+        };
+        Ok(keyring)
+    }
 }
 
 #[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u32)]
 pub enum KeyCtlOperation {
     /// Ask for a keyring's ID
@@ -104,7 +175,7 @@ pub enum KeyCtlOperation {
     /// Reject a partially constructed key
     Reject = 19,
     /// Instantiate a partially constructed key
-    InstantiageIov = 20,
+    InstantiateIov = 20,
     /// Invalidate a key
     Invalidate = 21,
     /// Get a user's persistent keyring
@@ -131,6 +202,116 @@ pub enum KeyCtlOperation {
     WatchKey = 32,
 }
 
+/// Flag for KEYCTL_MOVE, fail if a matching key is already linked to the destination
+pub const KEYCTL_MOVE_EXCL: libc::c_ulong = 0x01;
+
+/// Flag for pipe2() to create a notification pipe, shares the value of O_EXCL
+pub const O_NOTIFICATION_PIPE: libc::c_int = libc::O_EXCL;
+
+/// ioctl() to set the number of notification slots of a notification pipe
+pub const IOC_WATCH_QUEUE_SET_SIZE: libc::c_ulong = 0x5760;
+
+/// Notification record types, mirrors `enum watch_notification_type`
+pub const WATCH_TYPE_META: u32 = 0;
+pub const WATCH_TYPE_KEY_NOTIFY: u32 = 1;
+
+/// Meta notification subtypes, mirrors `enum watch_meta_notification_subtype`
+pub const WATCH_META_REMOVAL_NOTIFICATION: u8 = 0;
+pub const WATCH_META_LOSS_NOTIFICATION: u8 = 1;
+
+/// Masks for the info field of `struct watch_notification`
+pub const WATCH_INFO_LENGTH: u32 = 0x0000007f;
+pub const WATCH_INFO_ID: u32 = 0x0000ff00;
+pub const WATCH_INFO_ID_SHIFT: u32 = 8;
+
+/// Key notification subtypes, mirrors `enum key_notification_subtype`
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum KeyNotificationSubtype {
+    /// Key was instantiated
+    Instantiated = 0,
+    /// Key was updated
+    Updated = 1,
+    /// Key was added to watched keyring
+    Linked = 2,
+    /// Key was removed from watched keyring
+    Unlinked = 3,
+    /// Keyring was cleared
+    Cleared = 4,
+    /// Key was revoked
+    Revoked = 5,
+    /// Key was invalidated
+    Invalidated = 6,
+    /// Key's attributes got changed
+    SetAttr = 7,
+}
+
+/// Result of KEYCTL_PKEY_QUERY, mirrors `struct keyctl_pkey_query`.
+#[allow(dead_code)]
+#[derive(Debug, Default, Copy, Clone)]
+#[repr(C)]
+pub struct KeyCtlPkeyQuery {
+    /// Which ops are supported
+    pub supported_ops: u32,
+    /// Size of the key in bits
+    pub key_size: u32,
+    /// Maximum size of raw data to sign in bytes
+    pub max_data_size: u16,
+    /// Maximum size of signature in bytes
+    pub max_sig_size: u16,
+    /// Maximum size of encrypted blob in bytes
+    pub max_enc_size: u16,
+    /// Maximum size of decrypted blob in bytes
+    pub max_dec_size: u16,
+    /// Reserved
+    pub spare: [u32; 10],
+}
+
+/// Parameters for the public key operations, mirrors `struct keyctl_pkey_params`.
+#[allow(dead_code)]
+#[derive(Debug, Default, Copy, Clone)]
+#[repr(C)]
+pub struct KeyCtlPkeyParams {
+    /// Serial ID of the key to use
+    pub key_id: i32,
+    /// Input data size
+    pub in_len: u32,
+    /// Output buffer size (encrypt/decrypt/sign) or
+    /// signature size (verify)
+    pub out_len: u32,
+    /// Reserved
+    pub spare: [u32; 7],
+}
+
+/// Key triplet for KEYCTL_DH_COMPUTE, mirrors `struct keyctl_dh_params`.
+#[allow(dead_code)]
+#[derive(Debug, Default, Copy, Clone)]
+#[repr(C)]
+pub struct KeyCtlDhParams {
+    /// Serial ID of the key holding the private value
+    pub private: i32,
+    /// Serial ID of the key holding the prime
+    pub prime: i32,
+    /// Serial ID of the key holding the base
+    pub base: i32,
+}
+
+/// KDF parameters for KEYCTL_DH_COMPUTE, mirrors `struct keyctl_kdf_params`.
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct KeyCtlKdfParams {
+    /// Name of the hash used by the KDF
+    pub hashname: *const libc::c_char,
+    /// Optional other info to mix into the KDF
+    pub otherinfo: *const libc::c_char,
+    /// Length of the other info
+    pub otherinfolen: u32,
+    /// Reserved
+    pub spare: [u32; 8],
+}
+
 impl KeySerialId {
     /// Construct from a raw i32
     pub fn new(raw: i32) -> Self {
@@ -143,21 +324,34 @@ impl KeySerialId {
     }
 }
 
-/// Perform the conversion here so that invalid KeyType strings cannot be used.
-/// Using Rust's type system to ensure only valid strings are provided to the syscall.
-impl From<KeyType> for &'static CStr {
-    fn from(t: KeyType) -> &'static CStr {
-        match t {
+impl KeyType {
+    /// The name of this type as understood by the kernel.
+    pub fn as_cstr(&self) -> &CStr {
+        match self {
             KeyType::KeyRing => c"keyring",
             KeyType::User => c"user",
             KeyType::Logon => c"logon",
             KeyType::BigKey => c"big_key",
+            KeyType::Asymmetric => c"asymmetric",
+            KeyType::Encrypted => c"encrypted",
+            KeyType::Trusted => c"trusted",
+            KeyType::DnsResolver => c"dns_resolver",
+            KeyType::RequestKeyAuth => c".request_key_auth",
+            KeyType::Blacklist => c"blacklist",
+            KeyType::IdResolver => c"id_resolver",
+            KeyType::Ceph => c"ceph",
+            KeyType::RxRpc => c"rxrpc",
+            KeyType::RxRpcServer => c"rxrpc_s",
+            KeyType::CifsIdmap => c"cifs.idmap",
+            KeyType::CifsSpnego => c"cifs.spnego",
+            KeyType::Other(name) => name.as_c_str(),
         }
     }
 }
 
 /// Perform the conversion here so that invalid KeyType strings cannot be used.
-/// Using Rust's type system to ensure only valid strings are provided to the syscall.
+/// Names that do not match a known type are kept as [KeyType::Other], provided
+/// they are non-empty and contain no interior null bytes.
 impl TryFrom<&str> for KeyType {
     type Error = KeyError;
     fn try_from(s: &str) -> Result<Self, Self::Error> {
@@ -166,7 +360,20 @@ impl TryFrom<&str> for KeyType {
             "user" => KeyType::User,
             "logon" => KeyType::Logon,
             "big_key" => KeyType::BigKey,
-            _ => return Err(KeyError::InvalidIdentifier),
+            "asymmetric" => KeyType::Asymmetric,
+            "encrypted" => KeyType::Encrypted,
+            "trusted" => KeyType::Trusted,
+            "dns_resolver" => KeyType::DnsResolver,
+            ".request_key_auth" => KeyType::RequestKeyAuth,
+            "blacklist" => KeyType::Blacklist,
+            "id_resolver" => KeyType::IdResolver,
+            "ceph" => KeyType::Ceph,
+            "rxrpc" => KeyType::RxRpc,
+            "rxrpc_s" => KeyType::RxRpcServer,
+            "cifs.idmap" => KeyType::CifsIdmap,
+            "cifs.spnego" => KeyType::CifsSpnego,
+            "" => return Err(KeyError::InvalidIdentifier),
+            other => KeyType::Other(CString::new(other).or(Err(KeyError::InvalidIdentifier))?),
         };
         Ok(val)
     }
@@ -194,3 +401,44 @@ impl TryFrom<i64> for KeySerialId {
         Ok(Self(n.try_into().or(Err(KeyError::InvalidIdentifier))?))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_type_roundtrip() {
+        for name in [
+            "keyring",
+            "user",
+            "logon",
+            "big_key",
+            "asymmetric",
+            "encrypted",
+            "trusted",
+            "dns_resolver",
+            ".request_key_auth",
+            "blacklist",
+            "id_resolver",
+            "ceph",
+            "rxrpc",
+            "rxrpc_s",
+            "cifs.idmap",
+            "cifs.spnego",
+            "my_custom_type",
+        ] {
+            let ktype = KeyType::try_from(name).unwrap();
+            assert_eq!(ktype.as_cstr().to_str().unwrap(), name);
+        }
+    }
+
+    #[test]
+    fn test_key_type_other() {
+        let ktype = KeyType::try_from("pkcs7_test").unwrap();
+        assert_eq!(ktype, KeyType::Other(CString::new("pkcs7_test").unwrap()));
+
+        // Empty names and interior nulls cannot be passed to the kernel
+        assert_eq!(KeyType::try_from(""), Err(KeyError::InvalidIdentifier));
+        assert_eq!(KeyType::try_from("a\0b"), Err(KeyError::InvalidIdentifier));
+    }
+}
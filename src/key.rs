@@ -1,6 +1,11 @@
-use crate::ffi::{self, KeyCtlOperation, KeySerialId};
-use crate::utils::Vec;
-use crate::{KeyError, KeyPermissions, Metadata};
+use crate::ffi::{
+    self, KeyCtlDhParams, KeyCtlKdfParams, KeyCtlOperation, KeyCtlPkeyParams, KeyCtlPkeyQuery,
+    KeySerialId, ReadBuffer,
+};
+use crate::utils::{String, Vec};
+use crate::{
+    DhKdf, ExtendedMetadata, KeyError, KeyPermissions, LockedBuffer, Metadata, PkeyInfo, PkeyQuery,
+};
 use core::fmt;
 
 /// A key corresponding to a specific real ID.
@@ -58,6 +63,32 @@ impl Key {
         Metadata::from_id(self.0)
     }
 
+    /// Obtain information describing the attributes of this key, including
+    /// its LSM security label (see [Key::security_label]).
+    ///
+    /// Requires an additional call to the kernel compared to [Key::metadata].
+    /// The key must grant the caller view permission.
+    pub fn metadata_with_label(&self) -> Result<Metadata, KeyError> {
+        Metadata::from_id_with_label(self.0)
+    }
+
+    /// Obtain the state, usage count and expiry of this key in addition
+    /// to its attributes, by parsing its entry in `/proc/keys`.
+    ///
+    /// The key must grant the caller view permission.
+    pub fn extended_metadata(&self) -> Result<ExtendedMetadata, KeyError> {
+        ExtendedMetadata::from_id(self.0)
+    }
+
+    /// Obtain the LSM security label attached to this key, such as its
+    /// SELinux context.
+    ///
+    /// The label is empty when no LSM is active. The key must grant the
+    /// caller view permission.
+    pub fn security_label(&self) -> Result<String, KeyError> {
+        Metadata::security_label(self.0)
+    }
+
     /// Read the payload data of a key into a provided mutable slice.
     ///
     /// The returned usize is the number of bytes read into the slice.
@@ -78,26 +109,35 @@ impl Key {
 
     /// Read the payload data of a key, returning a newly allocated vector.
     ///
+    /// The vector is sized to hold the complete payload, which for a
+    /// [KeyType::BigKey](crate::KeyType::BigKey) may be up to 1 MiB. Should
+    /// the payload keep changing size while it is being read this will fail
+    /// with [KeyError::ContentChanged].
+    ///
     /// The key must either grant the caller read permission, or grant
     /// the caller search permission when searched for from the process
     /// keyrings (i.e., the key is possessed).
     pub fn read_to_vec(&self) -> Result<Vec<u8>, KeyError> {
-        // Ensure we have enough room to write up to the maximum for a UserKey
-        let mut buffer = Vec::with_capacity(65536);
+        ffi::keyctl_read_alloc(KeyCtlOperation::Read, self.0)
+    }
 
-        // Obtain the key
-        let len = ffi::keyctl!(
-            KeyCtlOperation::Read,
-            self.0.as_raw_id() as libc::c_ulong,
-            buffer.as_mut_ptr() as _,
-            buffer.capacity() as _
-        )? as usize;
+    /// Read the payload data of a key, returning a newly allocated vector that
+    /// is wiped once dropped.
+    ///
+    /// Behaves like [Key::read_to_vec], buffers that are discarded while the
+    /// payload is being read are wiped as well.
+    #[cfg(feature = "zeroize")]
+    pub fn read_secret(&self) -> Result<zeroize::Zeroizing<Vec<u8>>, KeyError> {
+        self.read_to_vec().map(zeroize::Zeroizing::new)
+    }
 
-        // Update length
-        unsafe {
-            buffer.set_len(len);
-        }
-        Ok(buffer)
+    /// Read the payload data of a key into a newly allocated [LockedBuffer],
+    /// keeping it out of swap and core dumps.
+    ///
+    /// Buffers are sized like [Key::read_to_vec], the buffers discarded while
+    /// the payload is being read are wiped as well.
+    pub fn read_locked(&self) -> Result<LockedBuffer, KeyError> {
+        ffi::keyctl_read_alloc(KeyCtlOperation::Read, self.0)
     }
 
     /// Update a key's data payload.
@@ -189,18 +229,39 @@ impl Key {
         Ok(())
     }
 
-    /// Mark a key as negatively instantiated and set an expiration timer on the key.
+    /// Mark a partially constructed key as negatively instantiated and set an
+    /// expiration timer on the key.
+    ///
+    /// Further searches for the key will fail with [KeyError::KeyDoesNotExist]
+    /// until the timeout expires. Equivalent to [Key::reject] with that error.
+    ///
+    /// To negate a key, the caller must have the appropriate authorization
+    /// key, see [Key::assume_authority].
+    pub fn negate(&self, seconds: usize) -> Result<(), KeyError> {
+        _ = ffi::keyctl!(
+            KeyCtlOperation::Negate,
+            self.0.as_raw_id() as libc::c_ulong,
+            seconds as _,
+            0
+        )?;
+        Ok(())
+    }
+
+    /// Mark a partially constructed key as negatively instantiated and set an
+    /// expiration timer on the key.
     ///
     /// This will prevent others from retrieving the key in further searches. And they
-    /// will receive a `EKEYREJECTED` error when performing the search.
+    /// will receive the provided error, such as [KeyError::KeyRejected] or
+    /// [KeyError::KeyExpired], when performing the search until the timeout expires.
     ///
-    /// Similar to [Key::revoke] but with a timeout.
-    pub fn reject(&self, seconds: usize) -> Result<(), KeyError> {
+    /// To reject a key, the caller must have the appropriate authorization
+    /// key, see [Key::assume_authority].
+    pub fn reject(&self, seconds: usize, error: KeyError) -> Result<(), KeyError> {
         _ = ffi::keyctl!(
             KeyCtlOperation::Reject,
             self.0.as_raw_id() as libc::c_ulong,
             seconds as _,
-            libc::EKEYREJECTED as _
+            error.as_errno() as _
         )?;
         Ok(())
     }
@@ -269,12 +330,291 @@ impl Key {
         )?;
         Ok(())
     }
+
+    /// Instantiate a partially constructed key from several buffers.
+    ///
+    /// The payload is the concatenation of the provided slices, allowing it to
+    /// be assembled from separate parts (e.g. a header and a secret) without
+    /// copying them into a temporary buffer. Otherwise behaves like [Key::instantiate].
+    #[cfg(any(test, feature = "std"))]
+    pub fn instantiate_iov(
+        &self,
+        payload: &[std::io::IoSlice<'_>],
+        id: KeySerialId,
+    ) -> Result<(), KeyError> {
+        // IoSlice is ABI compatible with struct iovec, when instantiating
+        // keyrings the payload will be NULL
+        let iov = match payload.len() {
+            0 => core::ptr::null(),
+            _ => payload.as_ptr(),
+        };
+        _ = ffi::keyctl!(
+            KeyCtlOperation::InstantiateIov,
+            self.0.as_raw_id() as libc::c_ulong,
+            iov as _,
+            payload.len() as _,
+            id.as_raw_id() as libc::c_ulong
+        )?;
+        Ok(())
+    }
+
+    /// Query the parameters of an asymmetric key, such as its size and the
+    /// operations it supports, for the provided info string.
+    ///
+    /// The key must grant the caller search permission. Keys that are not of
+    /// type [KeyType::Asymmetric](crate::KeyType::Asymmetric) will fail with
+    /// [KeyError::OperationNotSupported].
+    pub fn pkey_query(&self, info: &PkeyInfo) -> Result<PkeyQuery, KeyError> {
+        let info = info.to_cstring()?;
+        let mut result = KeyCtlPkeyQuery::default();
+        _ = ffi::keyctl!(
+            KeyCtlOperation::PubkeyQuery,
+            self.0.as_raw_id() as libc::c_ulong,
+            0,
+            info.as_ptr() as _,
+            &mut result as *mut KeyCtlPkeyQuery as _
+        )?;
+        Ok(result.into())
+    }
+
+    /// Encrypt the data using an asymmetric key, writing the result into the
+    /// provided mutable slice.
+    ///
+    /// The returned usize is the number of bytes written to the slice. The
+    /// required size of the slice can be obtained with [Key::pkey_query].
+    ///
+    /// The key must grant the caller search permission.
+    pub fn encrypt<D: AsRef<[u8]> + ?Sized, T: AsMut<[u8]> + ?Sized>(
+        &self,
+        info: &PkeyInfo,
+        data: &D,
+        buffer: &mut T,
+    ) -> Result<usize, KeyError> {
+        self.pkey_operation(
+            KeyCtlOperation::PubkeyEncrypt,
+            info,
+            data.as_ref(),
+            buffer.as_mut(),
+        )
+    }
+
+    /// Decrypt the data using an asymmetric key, writing the result into the
+    /// provided mutable slice.
+    ///
+    /// The returned usize is the number of bytes written to the slice. The
+    /// required size of the slice can be obtained with [Key::pkey_query].
+    ///
+    /// The key must grant the caller search permission and hold the private
+    /// key material.
+    pub fn decrypt<D: AsRef<[u8]> + ?Sized, T: AsMut<[u8]> + ?Sized>(
+        &self,
+        info: &PkeyInfo,
+        data: &D,
+        buffer: &mut T,
+    ) -> Result<usize, KeyError> {
+        self.pkey_operation(
+            KeyCtlOperation::PubkeyDecrypt,
+            info,
+            data.as_ref(),
+            buffer.as_mut(),
+        )
+    }
+
+    /// Sign the data (usually a digest) using an asymmetric key, writing the
+    /// signature into the provided mutable slice.
+    ///
+    /// The returned usize is the number of bytes written to the slice. The
+    /// required size of the slice can be obtained with [Key::pkey_query].
+    ///
+    /// The key must grant the caller search permission and hold the private
+    /// key material.
+    pub fn sign<D: AsRef<[u8]> + ?Sized, T: AsMut<[u8]> + ?Sized>(
+        &self,
+        info: &PkeyInfo,
+        data: &D,
+        buffer: &mut T,
+    ) -> Result<usize, KeyError> {
+        self.pkey_operation(
+            KeyCtlOperation::PubkeySign,
+            info,
+            data.as_ref(),
+            buffer.as_mut(),
+        )
+    }
+
+    /// Verify a signature over the data (usually a digest) using an asymmetric
+    /// key.
+    ///
+    /// A signature that does not match the data results in [KeyError::KeyRejected],
+    /// while a malformed signature may result in [KeyError::InvalidArguments].
+    ///
+    /// The key must grant the caller search permission.
+    pub fn verify<D: AsRef<[u8]> + ?Sized, S: AsRef<[u8]> + ?Sized>(
+        &self,
+        info: &PkeyInfo,
+        data: &D,
+        signature: &S,
+    ) -> Result<(), KeyError> {
+        let info = info.to_cstring()?;
+        let (data, signature) = (data.as_ref(), signature.as_ref());
+        let params = KeyCtlPkeyParams {
+            key_id: self.0.as_raw_id(),
+            in_len: data.len().try_into().or(Err(KeyError::InvalidArguments))?,
+            out_len: signature
+                .len()
+                .try_into()
+                .or(Err(KeyError::InvalidArguments))?,
+            ..Default::default()
+        };
+        _ = ffi::keyctl!(
+            KeyCtlOperation::PubkeyVerify,
+            &params as *const KeyCtlPkeyParams as _,
+            info.as_ptr() as _,
+            data.as_ptr() as _,
+            signature.as_ptr() as _
+        )?;
+        Ok(())
+    }
+
+    /// Compute a Diffie-Hellman shared secret, using this key as the private
+    /// value, returning a newly allocated vector.
+    ///
+    /// The result is `base ^ private mod prime`. All three keys must be of
+    /// type [KeyType::User](crate::KeyType::User), hold big-endian integers,
+    /// and grant the caller read permission. When the base is the public
+    /// value of the remote party this yields the shared secret, without the
+    /// private value ever entering the address space of this process.
+    ///
+    /// Kernels built without DH support fail with
+    /// [KeyError::OperationNotSupported].
+    pub fn dh_compute(&self, prime: Key, base: Key) -> Result<Vec<u8>, KeyError> {
+        self.dh_compute_alloc(prime, base)
+    }
+
+    /// Compute a Diffie-Hellman shared secret as [Key::dh_compute] does,
+    /// returning a newly allocated vector that is wiped once dropped.
+    #[cfg(feature = "zeroize")]
+    pub fn dh_compute_secret(
+        &self,
+        prime: Key,
+        base: Key,
+    ) -> Result<zeroize::Zeroizing<Vec<u8>>, KeyError> {
+        self.dh_compute(prime, base).map(zeroize::Zeroizing::new)
+    }
+
+    /// Compute a Diffie-Hellman shared secret as [Key::dh_compute] does,
+    /// returning a newly allocated [LockedBuffer] that keeps the secret out
+    /// of swap and core dumps.
+    pub fn dh_compute_locked(&self, prime: Key, base: Key) -> Result<LockedBuffer, KeyError> {
+        self.dh_compute_alloc(prime, base)
+    }
+
+    /// Internal method to compute a Diffie-Hellman shared secret into a newly
+    /// allocated buffer
+    fn dh_compute_alloc<B: ReadBuffer>(&self, prime: Key, base: Key) -> Result<B, KeyError> {
+        let params = KeyCtlDhParams {
+            private: self.0.as_raw_id(),
+            prime: prime.0.as_raw_id(),
+            base: base.0.as_raw_id(),
+        };
+
+        // Probe the size of the result, which is the size of the prime
+        let len = ffi::keyctl!(
+            KeyCtlOperation::DiffieHellmanCompute,
+            &params as *const KeyCtlDhParams as _,
+            core::ptr::null_mut::<u8>() as _,
+            0
+        )? as usize;
+
+        // Perform the computation
+        let mut buffer = B::allocate(len)?;
+        let len = ffi::keyctl!(
+            KeyCtlOperation::DiffieHellmanCompute,
+            &params as *const KeyCtlDhParams as _,
+            buffer.as_mut().as_mut_ptr() as _,
+            buffer.as_mut().len() as _
+        )? as usize;
+        buffer.truncate(len);
+        Ok(buffer)
+    }
+
+    /// Compute a Diffie-Hellman shared secret as [Key::dh_compute] does, then
+    /// apply the provided key derivation function to it.
+    ///
+    /// The derived key fills the provided mutable slice entirely, the returned
+    /// usize is the number of bytes written. A [LockedBuffer] may be provided
+    /// to keep the derived key out of swap and core dumps.
+    pub fn dh_compute_kdf<T: AsMut<[u8]> + ?Sized>(
+        &self,
+        prime: Key,
+        base: Key,
+        kdf: &DhKdf,
+        buffer: &mut T,
+    ) -> Result<usize, KeyError> {
+        let params = KeyCtlDhParams {
+            private: self.0.as_raw_id(),
+            prime: prime.0.as_raw_id(),
+            base: base.0.as_raw_id(),
+        };
+
+        // The kernel expects NULL when no other info is provided
+        let hash = kdf.hash_cstring()?;
+        let info = kdf.get_other_info();
+        let kdf = KeyCtlKdfParams {
+            hashname: hash.as_ptr(),
+            otherinfo: match info.len() {
+                0 => core::ptr::null(),
+                _ => info.as_ptr() as _,
+            },
+            otherinfolen: info.len().try_into().or(Err(KeyError::InvalidArguments))?,
+            spare: [0; 8],
+        };
+
+        let buffer = buffer.as_mut();
+        let len = ffi::keyctl!(
+            KeyCtlOperation::DiffieHellmanCompute,
+            &params as *const KeyCtlDhParams as _,
+            buffer.as_mut_ptr() as _,
+            buffer.len() as _,
+            &kdf as *const KeyCtlKdfParams as _
+        )? as usize;
+        Ok(len)
+    }
+
+    /// Internal method to perform an encrypt, decrypt or sign operation
+    fn pkey_operation(
+        &self,
+        operation: KeyCtlOperation,
+        info: &PkeyInfo,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, KeyError> {
+        let info = info.to_cstring()?;
+        let params = KeyCtlPkeyParams {
+            key_id: self.0.as_raw_id(),
+            in_len: input.len().try_into().or(Err(KeyError::InvalidArguments))?,
+            out_len: output
+                .len()
+                .try_into()
+                .or(Err(KeyError::InvalidArguments))?,
+            ..Default::default()
+        };
+        let len = ffi::keyctl!(
+            operation,
+            &params as *const KeyCtlPkeyParams as _,
+            info.as_ptr() as _,
+            input.as_ptr() as _,
+            output.as_mut_ptr() as _
+        )? as usize;
+        Ok(len)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{KeyRing, KeyRingIdentifier, KeyType, Permission};
+    use crate::{KeyRing, KeyType, Permission, PkeyEncoding, PkeyOperations};
+    use std::io::IoSlice;
     use zeroize::Zeroizing;
 
     #[test]
@@ -287,73 +627,364 @@ mod tests {
     fn test_metadata() {
         let secret = "Test Data";
 
-        // Obtain the default User keyring
-        let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            // Create the key
+            let key = ring.add_key("my-info-key", secret).unwrap();
+
+            // Obtain and verify the info
+            let info = key.metadata().unwrap();
+            assert_eq!(info.get_type(), KeyType::User);
+            assert_eq!(info.get_uid(), unsafe { libc::geteuid() });
+            assert_eq!(info.get_gid(), unsafe { libc::getegid() });
+            assert_eq!(info.get_perms().bits(), 0x3F010000);
+            assert_eq!(info.get_description(), "my-info-key");
+
+            // Cleanup
+            key.invalidate().unwrap()
+        })
+        .unwrap();
+    }
 
-        // Create the key
-        let key = ring.add_key("my-info-key", secret).unwrap();
+    #[test]
+    fn test_security_label() {
+        KeyRing::with_new_session(|ring| {
+            let key = ring.add_key("my-label-key", "Test Data").unwrap();
+
+            // The label is only obtained on request
+            assert!(key.metadata().unwrap().get_security_label().is_none());
+
+            // And only reported in the metadata when an LSM is active
+            let label = key.security_label().unwrap();
+            let info = key.metadata_with_label().unwrap();
+            match info.get_security_label() {
+                Some(value) => assert_eq!(value, label),
+                None => assert!(label.is_empty()),
+            }
+            assert_eq!(ring.security_label().unwrap().is_empty(), label.is_empty());
+            let info = ring.metadata_with_label().unwrap();
+            assert_eq!(info.get_security_label().is_none(), label.is_empty());
+
+            // Cleanup
+            key.invalidate().unwrap()
+        })
+        .unwrap();
+    }
 
-        // Obtain and verify the info
-        let info = key.metadata().unwrap();
-        assert_eq!(info.get_type(), KeyType::User);
-        assert_eq!(info.get_uid(), unsafe { libc::geteuid() });
-        assert_eq!(info.get_gid(), unsafe { libc::getegid() });
-        assert_eq!(info.get_perms().bits(), 0x3F010000);
-        assert_eq!(info.get_description(), "my-info-key");
+    #[test]
+    fn test_negate_reject_unauthorized() {
+        KeyRing::with_new_session(|ring| {
+            let key = ring.add_key("my-negate-key", "Test Data").unwrap();
+
+            // Only a helper holding the authorization key may negate or reject
+            assert_eq!(key.negate(10).unwrap_err(), KeyError::PermissionDenied);
+            let result = key.reject(10, KeyError::KeyExpired);
+            assert_eq!(result.unwrap_err(), KeyError::PermissionDenied);
+
+            // The error itself is validated first
+            let result = key.reject(10, KeyError::Unknown(0));
+            assert_eq!(result.unwrap_err(), KeyError::InvalidArguments);
+
+            // Cleanup
+            key.invalidate().unwrap()
+        })
+        .unwrap();
+    }
 
-        // Cleanup
-        key.invalidate().unwrap()
+    #[test]
+    fn test_instantiate_iov_unauthorized() {
+        KeyRing::with_new_session(|ring| {
+            let key = ring.add_key("my-iov-key", "Test Data").unwrap();
+
+            // Only a helper holding the authorization key may instantiate
+            let payload = [IoSlice::new(b"header:"), IoSlice::new(b"secret")];
+            let result = key.instantiate_iov(&payload, ring.get_id());
+            assert_eq!(result.unwrap_err(), KeyError::PermissionDenied);
+
+            // Cleanup
+            key.invalidate().unwrap()
+        })
+        .unwrap();
     }
 
     #[test]
     fn test_read_into_vec() {
         let secret = "Test Data";
 
-        // Obtain the default User keyring
-        let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            // Create the key
+            let key = ring.add_key("vec-read-key", secret).unwrap();
+
+            // Verify the payload
+            let payload = key.read_to_vec().unwrap();
+            assert_eq!(secret.as_bytes(), &payload);
+            key.invalidate().unwrap();
+        })
+        .unwrap();
+    }
 
-        // Create the key
-        let key = ring.add_key("vec-read-key", secret).unwrap();
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn test_read_secret() {
+        KeyRing::with_new_session(|ring| {
+            let key = ring.add_key("my-secret-key", "Test Data").unwrap();
+            let secret = key.read_secret().unwrap();
+            assert_eq!(secret.as_slice(), b"Test Data");
+
+            // Cleanup
+            key.invalidate().unwrap()
+        })
+        .unwrap();
+    }
 
-        // Verify the payload
-        let payload = key.read_to_vec().unwrap();
-        assert_eq!(secret.as_bytes(), &payload);
-        key.invalidate().unwrap();
+    #[test]
+    fn test_read_into_vec_large() {
+        // The maximum payload of a user key
+        let secret = alloc::vec![0xA5u8; 32767];
+
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            // Create the key
+            let key = ring.add_key("vec-read-large-key", &secret).unwrap();
+
+            // Verify the payload is read in full
+            let payload = key.read_to_vec().unwrap();
+            assert_eq!(secret, payload);
+            key.invalidate().unwrap();
+        })
+        .unwrap();
     }
 
     #[test]
     fn test_user_keyring_add_key() {
         let secret = "Test Data";
 
-        // Obtain the default User keyring
-        let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
-
-        // Create the key
-        let key = ring.add_key("my-super-secret-test-key", secret).unwrap();
-
-        // A buffer that is ensured to be zeroed when
-        // out of scope
-        let mut buf = Zeroizing::new([0u8; 4096]);
-
-        // Allow P/U/G full permissions
-        let mut perms = KeyPermissions::new();
-        perms.set_posessor_perms(Permission::ALL);
-        perms.set_user_perms(Permission::ALL);
-        perms.set_group_perms(Permission::ALL);
-
-        // Set the permissions
-        key.set_perms(perms).unwrap();
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            // Create the key
+            let key = ring.add_key("my-super-secret-test-key", secret).unwrap();
+
+            // A buffer that is ensured to be zeroed when
+            // out of scope
+            let mut buf = Zeroizing::new([0u8; 4096]);
+
+            // Allow P/U/G full permissions
+            let mut perms = KeyPermissions::new();
+            perms.set_posessor_perms(Permission::ALL);
+            perms.set_user_perms(Permission::ALL);
+            perms.set_group_perms(Permission::ALL);
+
+            // Set the permissions
+            key.set_perms(perms).unwrap();
+
+            // Read the secret and verify it matches
+            let len = key.read(&mut buf).unwrap();
+            assert_eq!(secret.as_bytes(), &buf[..len]);
+
+            // Update it
+            key.update(&"wow".as_bytes()).unwrap();
+
+            // Verify it matches the new content
+            let len = key.read(&mut buf).unwrap();
+            assert_eq!("wow".as_bytes(), &buf[..len]);
+            key.invalidate().unwrap()
+        })
+        .unwrap();
+    }
 
-        // Read the secret and verify it matches
-        let len = key.read(&mut buf).unwrap();
-        assert_eq!(secret.as_bytes(), &buf[..len]);
+    #[test]
+    fn test_pkey_unsupported() {
+        KeyRing::with_new_session(|ring| {
+            let key = ring.add_key("pkey-user-key", b"data").unwrap();
+            let info = PkeyInfo::new().encoding(PkeyEncoding::Pkcs1).hash("sha256");
+
+            // User keys do not support the public key operations
+            let result = key.pkey_query(&info);
+            assert_eq!(result.unwrap_err(), KeyError::OperationNotSupported);
+
+            let mut buf = [0u8; 256];
+            let result = key.sign(&info, &[0u8; 32], &mut buf);
+            assert_eq!(result.unwrap_err(), KeyError::OperationNotSupported);
+
+            let result = key.verify(&info, &[0u8; 32], &buf);
+            assert_eq!(result.unwrap_err(), KeyError::OperationNotSupported);
+        })
+        .unwrap();
+    }
 
-        // Update it
-        key.update(&"wow".as_bytes()).unwrap();
+    #[test]
+    fn test_pkey_sign_verify() {
+        // Self-signed X.509 certificate for a 1024-bit RSA key
+        const CERT: &str = "3082021A30820183A003020102021447D774F174EDF81CE3B4BD8AA7D361907B\
+            4C55F9300D06092A864886F70D01010B0500301E311C301A06035504030C136C\
+            696E75782D6B65797574696C7320746573743020170D32363130313632333130\
+            32315A180F32313236303932323233313032315A301E311C301A06035504030C\
+            136C696E75782D6B65797574696C73207465737430819F300D06092A864886F7\
+            0D010101050003818D0030818902818100D8396A9CE17EE2D9306520FE0755F3\
+            702878BFE856E36A146836AE33FB2EDB531A420A1C4CD38A709BCAAACD42A562\
+            84B15C55D709A3A402482892D760361DF33E04FD5E76EBEAC176209CDAF04B81\
+            444BF9DE48647CB89426375A7BB70867296006DBB330063197B67B4C8242545F\
+            EFD99918F26FE85B7674508BBEA7894F150203010001A3533051301D0603551D\
+            0E04160414F97719D0B72F8BC74DCED0954D1AFB5841588244301F0603551D23\
+            041830168014F97719D0B72F8BC74DCED0954D1AFB5841588244300F0603551D\
+            130101FF040530030101FF300D06092A864886F70D01010B0500038181001FFE\
+            82F4A665DA8372922AB5936F014E38D36023A201813887FE35DD89D6246B376C\
+            25EFBD0DF37747D329117D6CBB0F20B2A31087A17086422DFD9FD438E6E2F99D\
+            1D3C5BFA36774CDF110C79458A9389CBCB075F4E0BBE8FC52E5CC9364B9A4722\
+            EC6DC6AB1E06F1DEA48081472866211FC5BC49DBC2E5C6229D1739EC45E1";
+        // The matching PKCS#8 private key
+        const PRIVATE: &str = "30820278020100300D06092A864886F70D0101010500048202623082025E0201\
+            0002818100D8396A9CE17EE2D9306520FE0755F3702878BFE856E36A146836AE\
+            33FB2EDB531A420A1C4CD38A709BCAAACD42A56284B15C55D709A3A402482892\
+            D760361DF33E04FD5E76EBEAC176209CDAF04B81444BF9DE48647CB89426375A\
+            7BB70867296006DBB330063197B67B4C8242545FEFD99918F26FE85B7674508B\
+            BEA7894F1502030100010281810094BF630D8BBF3B95C84A39D927F8C420EB66\
+            AE36CAD765D9A61BBFD9B8B9F25E0819585541B0E1733869C2F8B4198C5DD699\
+            BBDFD41CC42E61AFAFDD04AE1609456850F2B88B901D58DF4E2BE5130EDFCBBC\
+            A6692EBAE2A6E2D8ADF72F8CDCADAD1B09F782639322981CCD657FE96E42589F\
+            31E31D5E957A0C0627056CAD40FD024100EF09CBEA40BD0466039B455A77B0EE\
+            2EEC51F1F62319025E8D72B6F03B1B2296CDBDA6076BD922D03D75CF674E6E52\
+            7ECC9A33BBB88D7F9B9D7C6B5F16AC656B024100E7913266E91CA40ADE5CCE1E\
+            17008B15731E3FE1D2418FA502C6B662C1E32017CCA5D36557FD4E9CB586942F\
+            701325C32FA2952375CA76C7AAB6BDE06929BD7F02400325CEE09BCFA1623283\
+            F5EAD60EA76448CA5F744D4C0402F8BBDCFAD6DC699D30C2619051743F881C77\
+            13AF19924C55CD1DEEB6771CADE47FB366BA8ED2E057024100A6B72B5159FD2B\
+            C1436C0F5D2C7450AE05AE3E91C73D3A3CB3ADFABB4919CB0E37611FBBF3C843\
+            E3A0F11E18B518A3F44D8440D941C19D188D3879A56A3109E3024100850F57CD\
+            96759E72020A76DACA458F32897C416DBDFAE9A6288057C55F83C85A34AE0000\
+            1C1A7BBBC665DB6DAFDC92DD661F1EF2074169139F90889911715727";
+        // SHA-256 digest of "linux-keyutils"
+        const DIGEST: &str = "EB2F058B38D2B1D8A3963AA81A9678CDBBE6B38DABBC096BD9FE2D023267EB8A";
+        // RSASSA-PKCS1-v1_5 signature of the digest
+        const SIGNATURE: &str = "00573AE897EE93F81B1381CACD42084BAE56836747D18151554E6C9C9B57037C\
+            B0C99BE84C3BF29CFC2A00AB1C7405A7F8B446D453792D2D63B7946C589C4705\
+            AE0B9A5C63A3C9FF1285AA8CCA16C458B5A60BB94DA5F7D3EB37713CF84563C1\
+            1D4E36D8D620A2A2E2AB70FFA01CD02260E3D677C6BC46744ABB844C66B4CB19";
+        let hex = |s: &str| -> Vec<u8> {
+            (0..s.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+                .collect()
+        };
+        let (digest, signature) = (hex(DIGEST), hex(SIGNATURE));
+        let mut other = digest.clone();
+        other[0] ^= 0x01;
+
+        KeyRing::with_new_session(|ring| {
+            let info = PkeyInfo::new().encoding(PkeyEncoding::Pkcs1).hash("sha256");
+
+            // Skip kernels without asymmetric keys or an X.509 parser
+            let public = match ring.add_key_with_type(KeyType::Asymmetric, "pkey-cert", &hex(CERT))
+            {
+                Err(KeyError::OperationNotSupported)
+                | Err(KeyError::MissingFileOrDirectory)
+                | Err(KeyError::Unknown(libc::ENODEV))
+                | Err(KeyError::Unknown(libc::EBADMSG)) => return,
+                result => result.unwrap(),
+            };
+
+            // A public key may only encrypt and verify
+            let query = public.pkey_query(&info).unwrap();
+            assert_eq!(
+                query.get_supported_ops(),
+                PkeyOperations::ENCRYPT | PkeyOperations::VERIFY
+            );
+            assert_eq!(query.get_key_size(), 1024);
+            assert_eq!(query.get_max_data_size(), 128);
+            assert_eq!(query.get_max_sig_size(), 128);
+            assert_eq!(query.get_max_enc_size(), 128);
+
+            // The signature only verifies the signed digest
+            public.verify(&info, &digest, &signature).unwrap();
+            let result = public.verify(&info, &other, &signature);
+            assert_eq!(result.unwrap_err(), KeyError::KeyRejected);
+
+            // Corrupted signatures are malformed, the exact error depends on
+            // the kernel version
+            let mut corrupted = signature.clone();
+            corrupted[64] ^= 0x01;
+            assert!(public.verify(&info, &digest, &corrupted).is_err());
+
+            // Signing requires the private key, skip kernels without a PKCS#8 parser
+            let private =
+                match ring.add_key_with_type(KeyType::Asymmetric, "pkey-private", &hex(PRIVATE)) {
+                    Err(KeyError::Unknown(libc::EBADMSG)) => return,
+                    result => result.unwrap(),
+                };
+            let query = private.pkey_query(&info).unwrap();
+            assert!(query.get_supported_ops().contains(PkeyOperations::SIGN));
+
+            // PKCS#1 v1.5 signatures are deterministic
+            let mut buf = [0u8; 128];
+            let len = private.sign(&info, &digest, &mut buf).unwrap();
+            assert_eq!(&buf[..len], &signature[..]);
+            private.verify(&info, &digest, &buf[..len]).unwrap();
+            let result = private.verify(&info, &other, &buf[..len]);
+            assert_eq!(result.unwrap_err(), KeyError::KeyRejected);
+        })
+        .unwrap();
+    }
 
-        // Verify it matches the new content
-        let len = key.read(&mut buf).unwrap();
-        assert_eq!("wow".as_bytes(), &buf[..len]);
-        key.invalidate().unwrap()
+    #[test]
+    fn test_dh_compute() {
+        // RFC 3526 1536-bit MODP group, the kernel rejects smaller primes
+        const PRIME: &str = "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD1\
+            29024E088A67CC74020BBEA63B139B22514A08798E3404DD\
+            EF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245\
+            E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+            EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3D\
+            C2007CB8A163BF0598DA48361C55D39A69163FA8FD24CF5F\
+            83655D23DCA3AD961C62F356208552BB9ED529077096966D\
+            670C354E4ABC9804F1746C08CA237327FFFFFFFFFFFFFFFF";
+        let prime: Vec<u8> = (0..PRIME.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&PRIME[i..i + 2], 16).unwrap())
+            .collect();
+
+        KeyRing::with_new_session(|ring| {
+            let prime = ring.add_key("dh-prime", &prime).unwrap();
+            let base = ring.add_key("dh-base", &[2u8]).unwrap();
+            let alice = ring.add_key("dh-alice", &[0x5Au8; 32]).unwrap();
+            let bob = ring.add_key("dh-bob", &[0xA5u8; 32]).unwrap();
+
+            // Compute the public values, skipping kernels without DH support
+            let alice_pub = match alice.dh_compute(prime, base) {
+                Err(KeyError::OperationNotSupported) => return,
+                result => result.unwrap(),
+            };
+            let bob_pub = bob.dh_compute(prime, base).unwrap();
+            assert_eq!(alice_pub.len(), 192);
+            assert_ne!(alice_pub, bob_pub);
+
+            // Both parties arrive at the same shared secret
+            let alice_pub = ring.add_key("dh-alice-pub", &alice_pub).unwrap();
+            let bob_pub = ring.add_key("dh-bob-pub", &bob_pub).unwrap();
+            let alice_shared = alice.dh_compute(prime, bob_pub).unwrap();
+            let bob_shared = bob.dh_compute(prime, alice_pub).unwrap();
+            assert_eq!(alice_shared, bob_shared);
+            let locked = alice.dh_compute_locked(prime, bob_pub).unwrap();
+            assert_eq!(&locked[..], &alice_shared[..]);
+            #[cfg(feature = "zeroize")]
+            assert_eq!(
+                *alice.dh_compute_secret(prime, bob_pub).unwrap(),
+                alice_shared
+            );
+
+            // As well as the same derived key
+            let kdf = DhKdf::new("sha256").other_info(b"test");
+            let mut alice_key = [0u8; 32];
+            let mut bob_key = [0u8; 32];
+            assert_eq!(
+                alice.dh_compute_kdf(prime, bob_pub, &kdf, &mut alice_key),
+                Ok(32)
+            );
+            assert_eq!(
+                bob.dh_compute_kdf(prime, alice_pub, &kdf, &mut bob_key),
+                Ok(32)
+            );
+            assert_eq!(alice_key, bob_key);
+        })
+        .unwrap();
     }
 }
@@ -1,7 +1,11 @@
 use crate::ffi::{self, KeyCtlOperation};
-use crate::utils::{CStr, CString, Vec};
-use crate::{Key, KeyError, KeyRingIdentifier, KeySerialId, KeyType, LinkNode, Links, Metadata};
+use crate::utils::{CString, String, Vec};
+use crate::{
+    DefaultKeyring, ExtendedMetadata, Key, KeyError, KeyRingIdentifier, KeySerialId, KeyType,
+    LinkNode, Links, Metadata, Restriction,
+};
 use core::convert::TryInto;
+use core::marker::PhantomData;
 
 /// Interface to perform keyring operations. Used to locate, create,
 /// search, add, and link/unlink keys to & from keyrings.
@@ -65,6 +69,112 @@ impl KeyRing {
         Ok(Self { id })
     }
 
+    /// Join or start a session keyring for the calling thread, replacing its
+    /// current session keyring.
+    ///
+    /// If a name is provided and a keyring with a matching description exists,
+    /// that keyring becomes the session keyring, provided it grants the caller
+    /// search permission (without relying on possession). Otherwise a new
+    /// keyring with that description is created. If no name is provided, a
+    /// new anonymous keyring with the description `_ses` is created.
+    ///
+    /// This is the equivalent of `keyctl session` and can be used to isolate
+    /// keys from the login session. Only the credentials of the calling thread
+    /// are changed, other threads keep their existing session keyring.
+    pub fn join_session(name: Option<&str>) -> Result<Self, KeyError> {
+        // The provided name must be properly null terminated for the kernel
+        let name = name
+            .map(CString::new)
+            .transpose()
+            .or(Err(KeyError::InvalidDescription))?;
+
+        // Passing NULL requests an anonymous keyring
+        let id = ffi::keyctl!(
+            KeyCtlOperation::JoinSessionKeyRing,
+            name.as_ref().map_or_else(core::ptr::null, |n| n.as_ptr()) as libc::c_ulong
+        )?;
+
+        // The kernel returns 0 if the named keyring is already the session keyring
+        if id == 0 {
+            return Self::from_special_id(KeyRingIdentifier::Session, false);
+        }
+        let id: KeySerialId = id.try_into().or(Err(KeyError::InvalidIdentifier))?;
+        Ok(Self { id })
+    }
+
+    /// Replace the session keyring of the parent process with the session
+    /// keyring of the calling thread.
+    ///
+    /// The parent's keyring is not replaced immediately, but the next time the
+    /// parent transitions from kernel space to user space. Combined with
+    /// [KeyRing::join_session] this allows helper programs, such as login
+    /// helpers, to install a new session keyring for the shell that invoked them.
+    ///
+    /// The kernel refuses with [KeyError::PermissionDenied] if any of the
+    /// following do not hold:
+    ///
+    /// * The parent process is single-threaded, and is not `init` or a kernel thread.
+    /// * The real, effective and saved UIDs of the parent all equal the
+    ///   effective UID of the caller, and likewise its GIDs equal the
+    ///   effective GID of the caller.
+    /// * The session keyring of the caller is owned by the caller's effective
+    ///   UID, as is the session keyring of the parent if it has one.
+    ///
+    /// The session keyring of the caller must also grant it link permission.
+    pub fn session_to_parent() -> Result<(), KeyError> {
+        _ = ffi::keyctl!(KeyCtlOperation::SessionToParent)?;
+        Ok(())
+    }
+
+    /// Set the destination keyring for keys requested by the calling thread
+    /// without a destination keyring, returning the previous setting. See
+    /// [DefaultKeyring].
+    ///
+    /// Passing [DefaultKeyring::NoChange] only queries the current setting.
+    /// The setting is part of the credentials of the calling thread, see
+    /// [DefaultKeyringGuard] to change it temporarily.
+    pub fn set_default_request_keyring(
+        keyring: DefaultKeyring,
+    ) -> Result<DefaultKeyring, KeyError> {
+        let previous = ffi::keyctl!(
+            KeyCtlOperation::SetRequestKeyKeyring,
+            keyring as libc::c_ulong
+        )?;
+        previous.try_into()
+    }
+
+    /// Run a closure inside a brand-new anonymous session keyring, returning
+    /// its result.
+    ///
+    /// The closure is executed on a dedicated thread that first joins a new
+    /// anonymous session (see [KeyRing::join_session]), and is passed that
+    /// keyring. Keys created in it are therefore invisible to the session of
+    /// the caller, and are released once the closure returns and nothing else
+    /// holds a reference to them. The thread keyring is equally fresh, but
+    /// the process and user keyrings are shared with the caller.
+    ///
+    /// This is useful to keep tests and sandboxed workers hermetic. Panics in
+    /// the closure are propagated to the caller.
+    #[cfg(any(test, feature = "std"))]
+    pub fn with_new_session<F, T>(f: F) -> Result<T, KeyError>
+    where
+        F: FnOnce(KeyRing) -> T + Send,
+        T: Send,
+    {
+        std::thread::scope(|scope| {
+            let worker = scope.spawn(|| Self::join_session(None).map(f));
+            match worker.join() {
+                Ok(result) => result,
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        })
+    }
+
+    /// Obtain a copy of the ID of this keyring
+    pub fn get_id(&self) -> KeySerialId {
+        self.id
+    }
+
     /// Obtain information describing the attributes of this keyring.
     ///
     /// The keyring must grant the caller view permission.
@@ -72,6 +182,32 @@ impl KeyRing {
         Metadata::from_id(self.id)
     }
 
+    /// Obtain information describing the attributes of this keyring, including
+    /// its LSM security label (see [KeyRing::security_label]).
+    ///
+    /// Requires an additional call to the kernel compared to [KeyRing::metadata].
+    /// The keyring must grant the caller view permission.
+    pub fn metadata_with_label(&self) -> Result<Metadata, KeyError> {
+        Metadata::from_id_with_label(self.id)
+    }
+
+    /// Obtain the state, usage count and expiry of this keyring in addition
+    /// to its attributes, by parsing its entry in `/proc/keys`.
+    ///
+    /// The keyring must grant the caller view permission.
+    pub fn extended_metadata(&self) -> Result<ExtendedMetadata, KeyError> {
+        ExtendedMetadata::from_id(self.id)
+    }
+
+    /// Obtain the LSM security label attached to this keyring, such as
+    /// its SELinux context.
+    ///
+    /// The label is empty when no LSM is active. The keyring must grant
+    /// the caller view permission.
+    pub fn security_label(&self) -> Result<String, KeyError> {
+        Metadata::security_label(self.id)
+    }
+
     /// Creates or updates a key of the given description and User type,
     /// instantiates it with the payload of length plen, attaches it to the
     /// keyring.
@@ -85,9 +221,25 @@ impl KeyRing {
         &self,
         description: &D,
         secret: &S,
+    ) -> Result<Key, KeyError> {
+        self.add_key_with_type(KeyType::User, description, secret)
+    }
+
+    /// Creates or updates a key of the given type and description,
+    /// instantiates it with the payload, and attaches it to the keyring.
+    ///
+    /// Behaves like [KeyRing::add_key] but allows any key type the kernel
+    /// understands to be used. For example a [KeyType::Logon] key for
+    /// dm-crypt or a [KeyType::BigKey] for payloads larger than a user
+    /// key can hold. To create a new keyring use [KeyRing::add_keyring].
+    pub fn add_key_with_type<D: AsRef<str> + ?Sized, S: AsRef<[u8]> + ?Sized>(
+        &self,
+        ktype: KeyType,
+        description: &D,
+        secret: &S,
     ) -> Result<Key, KeyError> {
         let id = ffi::add_key(
-            KeyType::User,
+            &ktype,
             self.id.as_raw_id() as libc::c_ulong,
             description.as_ref(),
             Some(secret.as_ref()),
@@ -95,6 +247,21 @@ impl KeyRing {
         Ok(Key::from_id(id))
     }
 
+    /// Creates a new keyring with the given description and links it to
+    /// this keyring.
+    ///
+    /// If this keyring already contains a keyring with the same description,
+    /// the new keyring will displace the link to the extant one.
+    pub fn add_keyring<D: AsRef<str> + ?Sized>(&self, description: &D) -> Result<Self, KeyError> {
+        let id = ffi::add_key(
+            &KeyType::KeyRing,
+            self.id.as_raw_id() as libc::c_ulong,
+            description.as_ref(),
+            None,
+        )?;
+        Ok(Self::from_id(id))
+    }
+
     /// Attempts to find a key of the given type with a description that
     /// matches the specified description. If such a key could not be found,
     /// then the key is optionally created.
@@ -112,9 +279,20 @@ impl KeyRing {
         &self,
         description: &D,
         callout: Option<&C>,
+    ) -> Result<Key, KeyError> {
+        self.request_key_with_type(KeyType::User, description, callout)
+    }
+
+    /// Behaves like [KeyRing::request_key] but allows the type of the
+    /// requested key to be specified.
+    pub fn request_key_with_type<D: AsRef<str> + ?Sized, C: AsRef<str> + ?Sized>(
+        &self,
+        ktype: KeyType,
+        description: &D,
+        callout: Option<&C>,
     ) -> Result<Key, KeyError> {
         let id = ffi::request_key(
-            KeyType::User,
+            &ktype,
             self.id.as_raw_id() as libc::c_ulong,
             description.as_ref(),
             callout.map(|c| c.as_ref()),
@@ -134,53 +312,69 @@ impl KeyRing {
     ///
     /// If the key is found, its ID is returned as the function result.
     pub fn search<D: AsRef<str> + ?Sized>(&self, description: &D) -> Result<Key, KeyError> {
+        self.search_with_type(KeyType::User, description)
+    }
+
+    /// Behaves like [KeyRing::search] but allows the type of the key
+    /// being searched for to be specified.
+    ///
+    /// To locate a linked keyring by name use [KeyRing::search_keyring].
+    pub fn search_with_type<D: AsRef<str> + ?Sized>(
+        &self,
+        ktype: KeyType,
+        description: &D,
+    ) -> Result<Key, KeyError> {
+        Ok(Key::from_id(self.search_id(&ktype, description.as_ref())?))
+    }
+
+    /// Search for a keyring in the keyring tree, starting with this keyring
+    /// as the head.
+    ///
+    /// The same permission rules as [KeyRing::search] apply.
+    pub fn search_keyring<D: AsRef<str> + ?Sized>(
+        &self,
+        description: &D,
+    ) -> Result<Self, KeyError> {
+        Ok(Self::from_id(
+            self.search_id(&KeyType::KeyRing, description.as_ref())?,
+        ))
+    }
+
+    /// Internal method to perform KEYCTL_SEARCH for the given type.
+    fn search_id(&self, ktype: &KeyType, description: &str) -> Result<KeySerialId, KeyError> {
         // The provided description must be properly null terminated for the kernel
-        let description =
-            CString::new(description.as_ref()).or(Err(KeyError::InvalidDescription))?;
+        let description = CString::new(description).or(Err(KeyError::InvalidDescription))?;
 
         // Perform the raw syscall and validate that the result is a valid ID
         let id: KeySerialId = ffi::keyctl!(
             KeyCtlOperation::Search,
             self.id.as_raw_id() as libc::c_ulong,
-            Into::<&'static CStr>::into(KeyType::User).as_ptr() as _,
+            ktype.as_cstr().as_ptr() as _,
             description.as_ptr() as _,
             0
         )?
         .try_into()
         .or(Err(KeyError::InvalidIdentifier))?;
-
-        // Construct a key object from the ID
-        Ok(Key::from_id(id))
+        Ok(id)
     }
 
     /// Obtain a list of the keys/keyrings linked to this keyring.
     ///
-    /// This method allocates, but you can provide a maximum number of entries
-    /// to read. Each returned entry is 4 bytes.
+    /// This method allocates a list large enough to hold every entry. Should
+    /// the keyring keep changing size while it is being read this will fail
+    /// with [KeyError::ContentChanged].
     ///
     /// The keyring must either grant the caller read permission, or grant
     /// the caller search permission.
-    pub fn get_links(&self, max: usize) -> Result<Links, KeyError> {
-        // Allocate the requested capacity
-        let mut buffer = Vec::<KeySerialId>::with_capacity(max);
-
-        // Perform the read
-        let len = ffi::keyctl!(
-            KeyCtlOperation::Read,
-            self.id.as_raw_id() as libc::c_ulong,
-            buffer.as_mut_ptr() as _,
-            buffer.capacity() as _
-        )? as usize;
-
-        // Set the size of the results
-        unsafe {
-            buffer.set_len(len / core::mem::size_of::<KeySerialId>());
-        }
+    pub fn get_links(&self) -> Result<Links, KeyError> {
+        // Read the raw list of IDs
+        let buffer: Vec<u8> = ffi::keyctl_read_alloc(KeyCtlOperation::Read, self.id)?;
 
         // Remap the results to complete keys
         Ok(buffer
-            .iter()
-            .filter_map(|&id| LinkNode::from_id(id).ok())
+            .chunks_exact(core::mem::size_of::<KeySerialId>())
+            .map(|raw| KeySerialId::new(i32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]])))
+            .filter_map(LinkNode::from_id)
             .collect())
     }
 
@@ -223,6 +417,29 @@ impl KeyRing {
         Ok(())
     }
 
+    /// Atomically move a key from this keyring to another keyring.
+    ///
+    /// The key is linked to the destination and unlinked from this keyring in
+    /// a single operation, so it is never present in both or neither.
+    ///
+    /// If a key with the same type and description is already linked in the
+    /// destination keyring it is displaced, unless exclusive is set. In which
+    /// case the move fails with [KeyError::AlreadyExists] and the key remains
+    /// linked to this keyring. This allows "publish only if absent" semantics.
+    ///
+    /// The caller must have link permission on the key, and write permission
+    /// on both keyrings.
+    pub fn move_key(&self, key: Key, to: KeyRing, exclusive: bool) -> Result<(), KeyError> {
+        _ = ffi::keyctl!(
+            KeyCtlOperation::Move,
+            key.get_id().as_raw_id() as _,
+            self.id.as_raw_id() as libc::c_ulong,
+            to.id.as_raw_id() as libc::c_ulong,
+            if exclusive { ffi::KEYCTL_MOVE_EXCL } else { 0 }
+        )?;
+        Ok(())
+    }
+
     /// Link another keyring to this keyring.
     ///
     /// Behaves similarly to link_key, but links a KeyRing instead. The caller
@@ -287,6 +504,41 @@ impl KeyRing {
         Ok(())
     }
 
+    /// Restrict the keys that may be linked to this keyring.
+    ///
+    /// The key type provided interprets the restriction, typically this is
+    /// [KeyType::Asymmetric] along with one of the [Restriction] schemes. Key
+    /// types that do not support restrictions fail with
+    /// [KeyError::MissingFileOrDirectory].
+    ///
+    /// A keyring can only be restricted once, further attempts fail with
+    /// [KeyError::AlreadyExists]. The caller must have setattr permission on
+    /// the keyring.
+    pub fn restrict(&self, kind: KeyType, restriction: Restriction) -> Result<(), KeyError> {
+        let restriction = restriction.to_cstring()?;
+        _ = ffi::keyctl!(
+            KeyCtlOperation::RestrictKeyring,
+            self.id.as_raw_id() as libc::c_ulong,
+            kind.as_cstr().as_ptr() as _,
+            restriction.as_ptr() as _
+        )?;
+        Ok(())
+    }
+
+    /// Prevent any further keys from being linked to this keyring.
+    ///
+    /// Links that already exist are unaffected and may still be removed.
+    /// The caller must have setattr permission on the keyring.
+    pub fn restrict_all(&self) -> Result<(), KeyError> {
+        _ = ffi::keyctl!(
+            KeyCtlOperation::RestrictKeyring,
+            self.id.as_raw_id() as libc::c_ulong,
+            0,
+            0
+        )?;
+        Ok(())
+    }
+
     /// Clear the contents of (i.e., unlink all keys from) this keyring.
     ///
     /// The caller must have write permission on the keyring.
@@ -296,10 +548,63 @@ impl KeyRing {
     }
 }
 
+/// Changes the destination keyring for implicitly requested keys, and
+/// restores the previous setting when dropped.
+///
+/// Since the setting belongs to the calling thread, the guard cannot be sent
+/// to another thread.
+///
+/// Usage:
+///
+/// ```
+/// use linux_keyutils::{DefaultKeyring, DefaultKeyringGuard, KeyError};
+///
+/// fn with_thread_keys() -> Result<(), KeyError> {
+///     let guard = DefaultKeyringGuard::new(DefaultKeyring::Thread)?;
+///
+///     // Keys requested here without a destination keyring, such as by
+///     // kernel subsystems, are linked into the thread keyring
+///
+///     // The previous setting is restored once the guard is dropped
+///     drop(guard);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DefaultKeyringGuard {
+    previous: DefaultKeyring,
+    _thread: PhantomData<*const ()>,
+}
+
+impl DefaultKeyringGuard {
+    /// Set the destination keyring for implicitly requested keys, see
+    /// [KeyRing::set_default_request_keyring].
+    pub fn new(keyring: DefaultKeyring) -> Result<Self, KeyError> {
+        let previous = KeyRing::set_default_request_keyring(keyring)?;
+        Ok(Self {
+            previous,
+            _thread: PhantomData,
+        })
+    }
+
+    /// The setting that will be restored
+    pub fn get_previous(&self) -> DefaultKeyring {
+        self.previous
+    }
+}
+
+impl Drop for DefaultKeyringGuard {
+    fn drop(&mut self) {
+        _ = KeyRing::set_default_request_keyring(self.previous);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{KeyPermissionsBuilder, Permission};
+    use crate::utils::Vec;
+    use crate::{KeyPermissions, KeyPermissionsBuilder, Permission};
+    use alloc::format;
 
     #[test]
     fn test_from_special_id() {
@@ -340,46 +645,47 @@ mod test {
 
     #[test]
     fn test_search_existing_key() {
-        // Test that a keyring that normally doesn't exist by default is
-        // created when called.
-        let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
-        let key = ring.add_key("test_search", b"data").unwrap();
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            let key = ring.add_key("test_search", b"data").unwrap();
 
-        // Ensure we have search permission on the key
-        let perms = KeyPermissionsBuilder::builder()
-            .posessor(Permission::ALL)
-            .user(Permission::ALL)
-            .build();
+            // Ensure we have search permission on the key
+            let perms = KeyPermissionsBuilder::builder()
+                .posessor(Permission::ALL)
+                .user(Permission::ALL)
+                .build();
 
-        // Enforce perms
-        key.set_perms(perms).unwrap();
+            // Enforce perms
+            key.set_perms(perms).unwrap();
 
-        // Search should succeed
-        let result = ring.search("test_search").unwrap();
+            // Search should succeed
+            let result = ring.search("test_search").unwrap();
 
-        // Assert that the ID is the same
-        assert_eq!(key.get_id(), result.get_id());
+            // Assert that the ID is the same
+            assert_eq!(key.get_id(), result.get_id());
 
-        // Request should also succeed
-        let result = ring.request_key("test_search", None::<&str>).unwrap();
+            // Request should also succeed
+            let result = ring.request_key("test_search", None::<&str>).unwrap();
 
-        // Assert that the ID is the same
-        assert_eq!(key.get_id(), result.get_id());
+            // Assert that the ID is the same
+            assert_eq!(key.get_id(), result.get_id());
 
-        // Invalidate the key
-        key.invalidate().unwrap();
+            // Invalidate the key
+            key.invalidate().unwrap();
+        })
+        .unwrap();
     }
 
     #[test]
     fn test_request_non_existing_key() {
-        // Test that a keyring that normally doesn't exist by default is
-        // created when called.
-        let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
-
-        let result = ring.request_key("test_request_no_exist", None::<&str>);
-
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), KeyError::KeyDoesNotExist);
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            let result = ring.request_key("test_request_no_exist", None::<&str>);
+
+            assert!(result.is_err());
+            assert_eq!(result.unwrap_err(), KeyError::KeyDoesNotExist);
+        })
+        .unwrap();
     }
 
     #[test]
@@ -387,139 +693,482 @@ mod test {
     fn test_request_non_existing_key_callout() {
         let callout = "Test Data from Callout";
 
-        // Test that a keyring that normally doesn't exist by default is
-        // created when called.
-        let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
-
-        // The test expects that the key is instantiated by a program invoked by
-        // /sbin/request-key and that the key data is taken from the callout info
-        // passed here.
-        //
-        // The following examples/keyctl command in /etc/request-key.conf is known to work:
-        // create	user	test_callout	*		/path/to/examples/keyctl instantiate --keyid %k --payload %c --ring %S
-        let key = ring.request_key("test_callout", Some(callout)).unwrap();
-
-        // Verify the payload
-        let payload = key.read_to_vec().unwrap();
-        assert_eq!(callout.as_bytes(), &payload);
-
-        // Invalidate the key
-        key.invalidate().unwrap();
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            // The test expects that the key is instantiated by a program invoked by
+            // /sbin/request-key and that the key data is taken from the callout info
+            // passed here.
+            //
+            // The following examples/keyctl command in /etc/request-key.conf is known to work:
+            // create	user	test_callout	*		/path/to/examples/keyctl instantiate --keyid %k --payload %c --ring %S
+            let key = ring.request_key("test_callout", Some(callout)).unwrap();
+
+            // Verify the payload
+            let payload = key.read_to_vec().unwrap();
+            assert_eq!(callout.as_bytes(), &payload);
+
+            // Invalidate the key
+            key.invalidate().unwrap();
+        })
+        .unwrap();
     }
 
     #[test]
     fn test_search_non_existing_key() {
-        // Test that a keyring that normally doesn't exist by default is
-        // created when called.
-        let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
-
-        // Search should succeed
-        let result = ring.search("test_search_no_exist");
-
-        // Assert that the ID is the same
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), KeyError::KeyDoesNotExist);
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            // Search should succeed
+            let result = ring.search("test_search_no_exist");
+
+            // Assert that the ID is the same
+            assert!(result.is_err());
+            assert_eq!(result.unwrap_err(), KeyError::KeyDoesNotExist);
+        })
+        .unwrap();
     }
 
     #[test]
+    #[allow(clippy::let_unit_value)]
     fn test_link_unlink_keyrings() {
-        // Get a couple of unlinked keyrings to test
-        let sess = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
-        assert!(sess.id.as_raw_id() > 0);
-        let thread = KeyRing::from_special_id(KeyRingIdentifier::Thread, true).unwrap();
-        assert!(thread.id.as_raw_id() > 0);
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|sess| {
+            assert!(sess.id.as_raw_id() > 0);
+            let thread = KeyRing::from_special_id(KeyRingIdentifier::Thread, true).unwrap();
+            assert!(thread.id.as_raw_id() > 0);
+
+            // Assert that the keyrings are not linked
+            let items = sess.get_links().unwrap();
+            assert!(!items.contains(&thread));
+
+            // Link the keyrings
+            let _ = sess.link_keyring(thread).unwrap();
+
+            // Assert that the keyrings are now linked
+            let items = sess.get_links().unwrap();
+            assert!(items.contains(&thread));
+
+            // Unlink the keyrings
+            let _ = sess.unlink_keyring(thread).unwrap();
+
+            // Assert that the keyrings are unlinked again
+            let items = sess.get_links().unwrap();
+            assert!(!items.contains(&thread));
+        })
+        .unwrap();
+    }
+    #[test]
+    #[allow(clippy::let_unit_value)]
+    fn test_link_unlink_keyrings_with_id() {
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|sess| {
+            assert!(sess.id.as_raw_id() > 0);
+            let thread = KeyRing::from_special_id(KeyRingIdentifier::Thread, true).unwrap();
+            assert!(thread.id.as_raw_id() > 0);
+
+            // Assert that the keyrings are not linked
+            let items = sess.get_links().unwrap();
+            assert!(!items.contains(&thread));
+
+            // Link the keyrings
+            let _ = sess.link_keyring_id(KeyRingIdentifier::Thread).unwrap();
+
+            // Assert that the keyrings are now linked
+            let items = sess.get_links().unwrap();
+            assert!(items.contains(&thread));
+
+            // Unlink the keyrings
+            let _ = sess.unlink_keyring_id(KeyRingIdentifier::Thread).unwrap();
+
+            // Assert that the keyrings are unlinked again
+            let items = sess.get_links().unwrap();
+            assert!(!items.contains(&thread));
+        })
+        .unwrap();
+    }
 
-        // Assert that the keyrings are not linked
-        let items = sess.get_links(200).unwrap();
-        assert!(!items.contains(&thread));
+    #[test]
+    fn test_linking_nonexistent_keyrings() {
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|sess| {
+            assert!(sess.id.as_raw_id() > 0);
+
+            // Test that the target keyring doesn't exist
+            let thread = KeyRing::from_special_id(KeyRingIdentifier::Thread, false);
+            assert!(matches!(thread, Err(KeyError::KeyDoesNotExist)));
+
+            // Unlinking a non-existent keyring
+            let result = sess.unlink_keyring_id(KeyRingIdentifier::Thread);
+            assert!(matches!(result, Err(KeyError::KeyDoesNotExist)));
+
+            // Linking a non-existent keyring
+            sess.link_keyring_id(KeyRingIdentifier::Thread).unwrap();
+
+            // After attempting to link the special keyring, it will have been created
+            let sess = KeyRing::from_special_id(KeyRingIdentifier::Thread, false).unwrap();
+            assert!(sess.id.as_raw_id() > 0);
+        })
+        .unwrap();
+    }
 
-        // Link the keyrings
-        let _ = sess.link_keyring(thread).unwrap();
+    #[test]
+    #[allow(clippy::len_zero)]
+    fn test_get_linked_items() {
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            assert!(ring.id.as_raw_id() > 0);
 
-        // Assert that the keyrings are now linked
-        let items = sess.get_links(200).unwrap();
-        assert!(items.contains(&thread));
+            // Add the key
+            let key = ring.add_key("test_read_key", b"test").unwrap();
 
-        // Unlink the keyrings
-        let _ = sess.unlink_keyring(thread).unwrap();
+            // Obtain a list of the linked keys
+            let items = ring.get_links().unwrap();
 
-        // Assert that the keyrings are unlinked again
-        let items = sess.get_links(200).unwrap();
-        assert!(!items.contains(&thread));
-    }
-    #[test]
-    fn test_link_unlink_keyrings_with_id() {
-        // Get a couple of unlinked keyrings to test
-        let sess = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
-        assert!(sess.id.as_raw_id() > 0);
-        let thread = KeyRing::from_special_id(KeyRingIdentifier::Thread, true).unwrap();
-        assert!(thread.id.as_raw_id() > 0);
+            // Assert that the key is in the ring
+            assert!(items.len() > 0);
+            assert!(items.contains(&key));
 
-        // Assert that the keyrings are not linked
-        let items = sess.get_links(200).unwrap();
-        assert!(!items.contains(&thread));
+            // Use the alternate reference to the key
+            let key_ref = items.get(&key).unwrap().as_key().unwrap();
 
-        // Link the keyrings
-        let _ = sess.link_keyring_id(KeyRingIdentifier::Thread).unwrap();
+            // Invalidate the key
+            key_ref.invalidate().unwrap();
 
-        // Assert that the keyrings are now linked
-        let items = sess.get_links(200).unwrap();
-        assert!(items.contains(&thread));
+            // Assert that the key is no longer on the ring
+            let items = ring.get_links().unwrap();
+            assert!(!items.contains(&key));
+        })
+        .unwrap();
+    }
 
-        // Unlink the keyrings
-        let _ = sess.unlink_keyring_id(KeyRingIdentifier::Thread).unwrap();
+    #[test]
+    fn test_add_search_logon_key() {
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            // Logon keys require a "service:" prefix in the description
+            let key = ring
+                .add_key_with_type(KeyType::Logon, "test:logon_key", b"secret")
+                .unwrap();
+
+            // The key is found when searching for the correct type
+            let result = ring
+                .search_with_type(KeyType::Logon, "test:logon_key")
+                .unwrap();
+            assert_eq!(key.get_id(), result.get_id());
+
+            // But not when searching for a user key
+            let result = ring.search("test:logon_key");
+            assert_eq!(result.unwrap_err(), KeyError::KeyDoesNotExist);
+
+            // Logon keys cannot be read from user space
+            let mut buf = [0u8; 32];
+            assert!(key.read(&mut buf).is_err());
+
+            // Request should also succeed
+            let result = ring
+                .request_key_with_type(KeyType::Logon, "test:logon_key", None::<&str>)
+                .unwrap();
+            assert_eq!(key.get_id(), result.get_id());
+
+            key.invalidate().unwrap();
+        })
+        .unwrap();
+    }
 
-        // Assert that the keyrings are unlinked again
-        let items = sess.get_links(200).unwrap();
-        assert!(!items.contains(&thread));
+    #[test]
+    fn test_add_search_keyring() {
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            // Create a new keyring linked to the session
+            let child = ring.add_keyring("test_add_keyring").unwrap();
+            assert_eq!(child.metadata().unwrap().get_type(), KeyType::KeyRing);
+            assert!(ring.get_links().unwrap().contains(&child));
+
+            // Locate it again by name
+            let result = ring.search_keyring("test_add_keyring").unwrap();
+            assert_eq!(child, result);
+
+            // Cleanup
+            ring.unlink_keyring(child).unwrap();
+        })
+        .unwrap();
     }
 
     #[test]
-    fn test_linking_nonexistent_keyrings() {
-        // Get existent keyring
-        let sess = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
-        assert!(sess.id.as_raw_id() > 0);
+    fn test_get_links_all_types() {
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            // Add a non-user key
+            let logon = ring
+                .add_key_with_type(KeyType::Logon, "test:links_logon", b"secret")
+                .unwrap();
+
+            // And a key that the caller cannot view
+            let hidden = ring.add_key("test_links_hidden", b"secret").unwrap();
+            hidden.set_perms(KeyPermissions::new()).unwrap();
+
+            // Both are present in the listing
+            let items = ring.get_links().unwrap();
+            let node = items.get(&logon).unwrap();
+            assert_eq!(node, &LinkNode::Other(logon, KeyType::Logon));
+            assert_eq!(node.as_key(), Some(logon));
+            let node = items.get(&hidden).unwrap();
+            assert_eq!(node, &LinkNode::Unavailable(hidden.get_id()));
+            assert!(items.contains(&hidden.get_id()));
+
+            // Cleanup
+            logon.invalidate().unwrap();
+            ring.unlink_key(hidden).unwrap();
+        })
+        .unwrap();
+    }
 
-        // Test that the target keyring doesn't exist
-        let thread = KeyRing::from_special_id(KeyRingIdentifier::Thread, false);
-        assert!(matches!(thread, Err(KeyError::KeyDoesNotExist)));
+    #[test]
+    fn test_get_links_sized() {
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            let child = ring.add_keyring("test_get_links_sized").unwrap();
+
+            // An empty keyring has no links
+            assert!(child.get_links().unwrap().is_empty());
+
+            // Populate the keyring
+            let keys: Vec<Key> = (0..64)
+                .map(|i| child.add_key(&format!("sized_{}", i), b"data").unwrap())
+                .collect();
+
+            // Every entry is returned
+            let items = child.get_links().unwrap();
+            assert_eq!(items.len(), keys.len());
+            assert!(keys.iter().all(|k| items.contains(k)));
+
+            // Cleanup
+            child.clear().unwrap();
+            ring.unlink_keyring(child).unwrap();
+        })
+        .unwrap();
+    }
 
-        // Unlinking a non-existent keyring
-        let result = sess.unlink_keyring_id(KeyRingIdentifier::Thread);
-        assert!(matches!(result, Err(KeyError::KeyDoesNotExist)));
+    #[test]
+    fn test_join_session() {
+        KeyRing::with_new_session(|original| {
+            // Start a new anonymous session for this test thread
+            let anon = KeyRing::join_session(None).unwrap();
+            assert_ne!(original, anon);
+            assert_eq!(anon.metadata().unwrap().get_description(), "_ses");
+            assert_eq!(
+                KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap(),
+                anon
+            );
+
+            // Joining a named session creates it on first use
+            let named = KeyRing::join_session(Some("test_join_session")).unwrap();
+            assert_ne!(anon, named);
+            assert_eq!(
+                named.metadata().unwrap().get_description(),
+                "test_join_session"
+            );
+
+            // And joins the existing keyring afterwards, provided it grants
+            // search permission without possession
+            let perms = KeyPermissionsBuilder::builder()
+                .posessor(Permission::ALL)
+                .user(Permission::VIEW | Permission::SEARCH)
+                .build();
+            Key::from_id(named.get_id()).set_perms(perms).unwrap();
+            KeyRing::join_session(None).unwrap();
+            let joined = KeyRing::join_session(Some("test_join_session")).unwrap();
+            assert_eq!(named, joined);
+
+            // Joining the current session keyring is a no-op
+            let joined = KeyRing::join_session(Some("test_join_session")).unwrap();
+            assert_eq!(named, joined);
+
+            // Invalid names are rejected
+            let result = KeyRing::join_session(Some("bad\0name"));
+            assert_eq!(result.unwrap_err(), KeyError::InvalidDescription);
+
+            // Cleanup
+            Key::from_id(named.get_id()).invalidate().unwrap();
+        })
+        .unwrap();
+    }
 
-        // Linking a non-existent keyring
-        sess.link_keyring_id(KeyRingIdentifier::Thread).unwrap();
+    #[test]
+    fn test_session_to_parent() {
+        // The parent must be single-threaded, unlike the test harness. So fork
+        // an intermediate parent, which in turn forks the child that replaces
+        // its session keyring. Only syscalls are made after forking.
+        let pid = unsafe { libc::fork() };
+        assert!(pid >= 0);
+        if pid == 0 {
+            let mut fds = [0 as libc::c_int; 2];
+            if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+                unsafe { libc::_exit(1) };
+            }
+
+            let child = unsafe { libc::fork() };
+            if child == 0 {
+                // Start a new session and install it on the parent
+                let code = match KeyRing::join_session(None) {
+                    Ok(ring) if KeyRing::session_to_parent().is_ok() => {
+                        let id = ring.get_id().as_raw_id().to_ne_bytes();
+                        unsafe { libc::write(fds[1], id.as_ptr() as _, id.len()) };
+                        0
+                    }
+                    _ => 1,
+                };
+                unsafe { libc::_exit(code) };
+            }
+
+            // Wait for the child, the replacement takes effect upon returning
+            let mut status = 0;
+            let mut id = [0u8; 4];
+            unsafe { libc::waitpid(child, &mut status, 0) };
+            let len = unsafe { libc::read(fds[0], id.as_mut_ptr() as _, id.len()) };
+            let session = KeyRing::from_special_id(KeyRingIdentifier::Session, false);
+            let code = match session {
+                Ok(ring) if len == 4 && ring.get_id().as_raw_id() == i32::from_ne_bytes(id) => 0,
+                _ => 2,
+            };
+            unsafe { libc::_exit(code) };
+        }
 
-        // After attempting to link the special keyring, it will have been created
-        let sess = KeyRing::from_special_id(KeyRingIdentifier::Thread, false).unwrap();
-        assert!(sess.id.as_raw_id() > 0);
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
     }
 
     #[test]
-    fn test_get_linked_items() {
-        // Test that a keyring that should already exist is returned
-        let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
-        assert!(ring.id.as_raw_id() > 0);
-
-        // Add the key
-        let key = ring.add_key("test_read_key", b"test").unwrap();
+    fn test_default_request_keyring() {
+        // The setting is per-thread, so run on a dedicated thread
+        KeyRing::with_new_session(|_| {
+            let original = KeyRing::set_default_request_keyring(DefaultKeyring::NoChange).unwrap();
+
+            // Setting returns the previous value
+            let previous = KeyRing::set_default_request_keyring(DefaultKeyring::Thread).unwrap();
+            assert_eq!(previous, original);
+            let current = KeyRing::set_default_request_keyring(DefaultKeyring::NoChange).unwrap();
+            assert_eq!(current, DefaultKeyring::Thread);
+
+            // The guard restores the previous value on drop
+            {
+                let guard = DefaultKeyringGuard::new(DefaultKeyring::Session).unwrap();
+                assert_eq!(guard.get_previous(), DefaultKeyring::Thread);
+                let current =
+                    KeyRing::set_default_request_keyring(DefaultKeyring::NoChange).unwrap();
+                assert_eq!(current, DefaultKeyring::Session);
+            }
+            let current = KeyRing::set_default_request_keyring(DefaultKeyring::NoChange).unwrap();
+            assert_eq!(current, DefaultKeyring::Thread);
+
+            // Restore the original setting
+            KeyRing::set_default_request_keyring(original).unwrap();
+        })
+        .unwrap();
+    }
 
-        // Obtain a list of the linked keys
-        let items = ring.get_links(200).unwrap();
+    #[test]
+    fn test_with_new_session() {
+        let outer = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
+
+        // The closure runs in a new session and its result is returned
+        let (inner, key) = KeyRing::with_new_session(|ring| {
+            let current = KeyRing::from_special_id(KeyRingIdentifier::Session, false).unwrap();
+            assert_eq!(ring, current);
+            (ring, ring.add_key("test_new_session", b"data").unwrap())
+        })
+        .unwrap();
+        assert_ne!(outer, inner);
+
+        // Keys created inside are not visible from the caller's session
+        assert!(!outer.get_links().unwrap().contains(&key));
+        let result = outer.search("test_new_session");
+        assert_eq!(result.unwrap_err(), KeyError::KeyDoesNotExist);
+    }
 
-        // Assert that the key is in the ring
-        assert!(items.len() > 0);
-        assert!(items.contains(&key));
+    #[test]
+    #[should_panic(expected = "inside session")]
+    fn test_with_new_session_panic() {
+        KeyRing::with_new_session(|_| panic!("inside session")).unwrap();
+    }
 
-        // Use the alternate reference to the key
-        let key_ref = items.get(&key).unwrap().as_key().unwrap();
+    #[test]
+    fn test_restrict_all() {
+        KeyRing::with_new_session(|ring| {
+            let child = ring.add_keyring("test_restrict_all").unwrap();
+            let key = child.add_key("before", b"data").unwrap();
+
+            // Lock down the keyring
+            child.restrict_all().unwrap();
+
+            // No new links may be created
+            let result = child.add_key("after", b"data");
+            assert_eq!(result.unwrap_err(), KeyError::PermissionDenied);
+
+            // Existing links may still be removed
+            child.unlink_key(key).unwrap();
+
+            // Keyrings can only be restricted once
+            let result = child.restrict_all();
+            assert_eq!(result.unwrap_err(), KeyError::AlreadyExists);
+        })
+        .unwrap();
+    }
 
-        // Invalidate the key
-        key_ref.invalidate().unwrap();
+    #[test]
+    fn test_restrict_asymmetric() {
+        KeyRing::with_new_session(|ring| {
+            let child = ring.add_keyring("test_restrict_trusted").unwrap();
+
+            // Skip kernels without asymmetric key support
+            match child.restrict(KeyType::Asymmetric, Restriction::BuiltinTrusted) {
+                Err(KeyError::MissingFileOrDirectory) => return,
+                result => result.unwrap(),
+            }
+
+            // Keys not signed by a trusted key are rejected
+            let result = child.add_key("untrusted", b"data");
+            assert!(result.is_err());
+
+            // Keyrings can only be restricted once
+            let result = child.restrict(KeyType::Asymmetric, Restriction::BuiltinTrusted);
+            assert_eq!(result.unwrap_err(), KeyError::AlreadyExists);
+
+            // And the type must support restrictions
+            let other = ring.add_keyring("test_restrict_user").unwrap();
+            let result = other.restrict(KeyType::User, Restriction::BuiltinTrusted);
+            assert_eq!(result.unwrap_err(), KeyError::MissingFileOrDirectory);
+        })
+        .unwrap();
+    }
 
-        // Assert that the key is no longer on the ring
-        let items = ring.get_links(200).unwrap();
-        assert!(!items.contains(&key));
+    #[test]
+    fn test_move_key() {
+        KeyRing::with_new_session(|ring| {
+            let from = ring.add_keyring("test_move_from").unwrap();
+            let to = ring.add_keyring("test_move_to").unwrap();
+            let key = from.add_key("test_move_key", b"data").unwrap();
+
+            // Move the key across
+            from.move_key(key, to, true).unwrap();
+            assert!(!from.get_links().unwrap().contains(&key));
+            assert!(to.get_links().unwrap().contains(&key));
+
+            // An exclusive move fails when a matching key is present
+            let other = from.add_key("test_move_key", b"other").unwrap();
+            let result = from.move_key(other, to, true);
+            assert_eq!(result.unwrap_err(), KeyError::AlreadyExists);
+            assert!(from.get_links().unwrap().contains(&other));
+            assert!(to.get_links().unwrap().contains(&key));
+
+            // While a non-exclusive move displaces it
+            from.move_key(other, to, false).unwrap();
+            let items = to.get_links().unwrap();
+            assert!(items.contains(&other));
+            assert!(!items.contains(&key));
+            assert!(from.get_links().unwrap().is_empty());
+        })
+        .unwrap();
     }
 }
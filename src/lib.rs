@@ -59,6 +59,10 @@
 // CString requires alloc however
 extern crate alloc;
 
+// Unit tests always have the std-lib available
+#[cfg(all(test, not(feature = "std")))]
+extern crate std;
+
 // Use the std-lib when available
 #[cfg(feature = "std")]
 mod utils {
@@ -80,7 +84,7 @@ mod utils {
 mod ffi;
 
 // Export certain FFI types
-pub use ffi::{KeyRingIdentifier, KeySerialId, KeyType};
+pub use ffi::{DefaultKeyring, KeyRingIdentifier, KeySerialId, KeyType};
 
 // Expose error types
 mod errors;
@@ -88,20 +92,65 @@ pub use errors::KeyError;
 
 // Primary keyring interface
 mod keyring;
-pub use keyring::KeyRing;
+pub use keyring::{DefaultKeyringGuard, KeyRing};
 
 // Primary key interface
 mod key;
 pub use key::Key;
 
+// Handles cleaning up nodes once dropped
+mod owned;
+pub use owned::{DropAction, OwnedKey, OwnedKeyRing};
+
+// Memory locked buffers for payloads
+mod locked;
+pub use locked::LockedBuffer;
+
 // Information about nodes (either keys or keyrings)
 mod metadata;
 pub use metadata::Metadata;
 
+// Information only available from /proc/keys
+mod extended;
+pub use extended::{ExtendedMetadata, KeyExpiry, KeyFlags};
+
 // Nodes in a ring/tree
 mod links;
 pub use links::{LinkNode, Links};
 
+// Public key operation parameters and results
+mod pkey;
+pub use pkey::{PkeyEncoding, PkeyInfo, PkeyOperations, PkeyQuery};
+
+// Diffie-Hellman computation parameters
+mod dh;
+pub use dh::DhKdf;
+
+// Keyring restriction schemes
+mod restrict;
+pub use restrict::Restriction;
+
+// Kernel feature detection
+mod capabilities;
+pub use capabilities::Capabilities;
+
+// Key change notifications
+mod watch;
+pub use watch::{KeyEvent, KeyNotification, KeyWatcher};
+
+// Async key change notifications
+#[cfg(feature = "tokio")]
+mod stream;
+#[cfg(feature = "tokio")]
+pub use stream::KeyEventStream;
+
+// Helpers invoked by the kernel to construct requested keys
+pub mod request_key;
+
+// Atomic creation of configured keys
+mod builder;
+pub use builder::KeyBuilder;
+
 // Expose KeyPermissions API
 mod permissions;
 pub use permissions::{KeyPermissions, KeyPermissionsBuilder, Permission};
@@ -7,10 +7,18 @@ use core::ops::Deref;
 
 /// An item/node linked to a ring. Both keys and other keyrings
 /// can be linked to a particular keyring.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LinkNode {
+    /// A linked keyring
     KeyRing(KeyRing),
+    /// A linked key of type [KeyType::User]
     Key(Key),
+    /// A linked key of any other type, such as [KeyType::Logon]
+    Other(Key, KeyType),
+    /// An entry that is linked to the ring, but whose attributes could not
+    /// be obtained. For example because the caller lacks view permission on
+    /// it, or because it has been revoked or has expired.
+    Unavailable(KeySerialId),
 }
 
 /// A collection of LinkNodes, returned from [KeyRing::get_links]
@@ -26,7 +34,7 @@ pub enum LinkNode {
 ///     let ring = KeyRing::from_special_id(KeyRingIdentifier::UserSession, false)?;
 ///
 ///     // Locate all the links
-///     let links = ring.get_links(200)?;
+///     let links = ring.get_links()?;
 ///
 ///     // Determine if the key is linked to the ring
 ///     Ok(links.contains(key))
@@ -37,46 +45,84 @@ pub struct Links(Vec<LinkNode>);
 
 impl PartialEq<Key> for LinkNode {
     fn eq(&self, other: &Key) -> bool {
-        matches!(self, LinkNode::Key(x) if x == other)
+        match self {
+            LinkNode::Key(x) | LinkNode::Other(x, _) => x == other,
+            LinkNode::Unavailable(id) => *id == other.get_id(),
+            LinkNode::KeyRing(_) => false,
+        }
     }
 }
 
 impl PartialEq<Key> for &LinkNode {
     fn eq(&self, other: &Key) -> bool {
-        matches!(self, LinkNode::Key(x) if x == other)
+        <LinkNode as PartialEq<Key>>::eq(self, other)
     }
 }
 
 impl PartialEq<KeyRing> for LinkNode {
     fn eq(&self, other: &KeyRing) -> bool {
-        matches!(self, LinkNode::KeyRing(x) if x == other)
+        match self {
+            LinkNode::KeyRing(x) => x == other,
+            LinkNode::Unavailable(id) => *id == other.get_id(),
+            LinkNode::Key(_) | LinkNode::Other(_, _) => false,
+        }
     }
 }
 
 impl PartialEq<KeyRing> for &LinkNode {
     fn eq(&self, other: &KeyRing) -> bool {
-        matches!(self, LinkNode::KeyRing(x) if x == other)
+        <LinkNode as PartialEq<KeyRing>>::eq(self, other)
+    }
+}
+
+impl PartialEq<KeySerialId> for LinkNode {
+    fn eq(&self, other: &KeySerialId) -> bool {
+        self.get_id() == *other
+    }
+}
+
+impl PartialEq<KeySerialId> for &LinkNode {
+    fn eq(&self, other: &KeySerialId) -> bool {
+        self.get_id() == *other
     }
 }
 
 impl LinkNode {
     /// Internal method to construct a LinkNode from a raw ID
-    pub(crate) fn from_id(id: KeySerialId) -> Result<Self, KeyError> {
-        let metadata = Metadata::from_id(id)?;
+    ///
+    /// Entries that cannot be described are kept as [LinkNode::Unavailable]
+    /// so that the listing remains a faithful view of the ring. Keys that no
+    /// longer exist, such as invalidated keys awaiting garbage collection,
+    /// are skipped.
+    pub(crate) fn from_id(id: KeySerialId) -> Option<Self> {
+        let metadata = match Metadata::from_id(id) {
+            Ok(metadata) => metadata,
+            Err(KeyError::KeyDoesNotExist) => return None,
+            Err(_) => return Some(Self::Unavailable(id)),
+        };
         let node = match metadata.get_type() {
             KeyType::KeyRing => Self::KeyRing(KeyRing::from_id(id)),
             KeyType::User => Self::Key(Key::from_id(id)),
-            _ => return Err(KeyError::OperationNotSupported),
+            other => Self::Other(Key::from_id(id), other),
         };
-        Ok(node)
+        Some(node)
+    }
+
+    /// Obtain the ID of this entry
+    pub fn get_id(&self) -> KeySerialId {
+        match self {
+            Self::KeyRing(inner) => inner.get_id(),
+            Self::Key(inner) | Self::Other(inner, _) => inner.get_id(),
+            Self::Unavailable(id) => *id,
+        }
     }
 
     /// Attempt to convert this LinkNode to a Key
     ///
-    /// Returns the key if the entry is a Key, None otherwise.
+    /// Returns the key if the entry is a Key of any type, None otherwise.
     pub fn as_key(&self) -> Option<Key> {
         match self {
-            Self::Key(inner) => Some(*inner),
+            Self::Key(inner) | Self::Other(inner, _) => Some(*inner),
             _ => None,
         }
     }
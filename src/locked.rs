@@ -0,0 +1,221 @@
+//! Memory locked buffers for key payloads
+//!
+use crate::ffi::ReadBuffer;
+use crate::KeyError;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::ptr::NonNull;
+
+/// A fixed capacity buffer for key payloads that is kept out of swap and
+/// core dumps, and wiped once dropped.
+///
+/// The buffer is backed by its own anonymous mapping which is locked into
+/// memory with `mlock(2)` and excluded from core dumps with `MADV_DONTDUMP`.
+/// Since it implements `AsRef<[u8]>` and `AsMut<[u8]>` it can be passed to
+/// [KeyRing::add_key](crate::KeyRing::add_key), [Key::update](crate::Key::update)
+/// and [Key::read](crate::Key::read), or obtained with
+/// [Key::read_locked](crate::Key::read_locked).
+///
+/// Locking memory is subject to `RLIMIT_MEMLOCK`, exceeding it fails with
+/// [KeyError::OutOfMemory] or [KeyError::PermissionDenied].
+///
+/// Usage:
+///
+/// ```
+/// use linux_keyutils::{KeyError, KeyRing, KeyRingIdentifier, LockedBuffer};
+///
+/// fn store_secret() -> Result<(), KeyError> {
+///     let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false)?;
+///
+///     // Assemble the payload in locked memory
+///     let mut payload = LockedBuffer::new(32)?;
+///     payload.copy_from_slice(&[0x42; 32]);
+///     let key = ring.add_key("my-locked-secret", &payload)?;
+///
+///     // And read it back the same way
+///     let secret = key.read_locked()?;
+///     assert_eq!(&secret[..], &payload[..]);
+///     Ok(())
+/// }
+/// ```
+pub struct LockedBuffer {
+    ptr: NonNull<u8>,
+    len: usize,
+    capacity: usize,
+}
+
+// The buffer uniquely owns its mapping, similar to a Vec
+unsafe impl Send for LockedBuffer {}
+unsafe impl Sync for LockedBuffer {}
+
+impl LockedBuffer {
+    /// Allocate a zeroed buffer of the provided length.
+    pub fn new(len: usize) -> Result<Self, KeyError> {
+        // Round up to whole pages, mappings cannot be empty
+        let page = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+            x if x > 0 => x as usize,
+            _ => 4096,
+        };
+        let capacity = len
+            .max(1)
+            .checked_next_multiple_of(page)
+            .ok_or(KeyError::OutOfMemory)?;
+
+        // Anonymous mappings are zero filled
+        let ptr = unsafe {
+            libc::mmap(
+                core::ptr::null_mut(),
+                capacity,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(KeyError::from_errno());
+        }
+        let buffer = Self {
+            ptr: NonNull::new(ptr as *mut u8).ok_or(KeyError::OutOfMemory)?,
+            len,
+            capacity,
+        };
+
+        // Keep the pages out of swap and core dumps
+        if unsafe { libc::mlock(ptr, capacity) } < 0 {
+            return Err(KeyError::from_errno());
+        }
+        if unsafe { libc::madvise(ptr, capacity, libc::MADV_DONTDUMP) } < 0 {
+            return Err(KeyError::from_errno());
+        }
+        Ok(buffer)
+    }
+
+    /// Shorten the buffer, has no effect if the length is not smaller than
+    /// the current length. The memory is not released.
+    pub fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+
+    /// The length of the buffer in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the buffer is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl ReadBuffer for LockedBuffer {
+    fn allocate(len: usize) -> Result<Self, KeyError> {
+        Self::new(len)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        LockedBuffer::truncate(self, len)
+    }
+}
+
+impl Drop for LockedBuffer {
+    fn drop(&mut self) {
+        // Wipe the complete mapping, volatile writes cannot be elided
+        let base = self.ptr.as_ptr();
+        for offset in 0..self.capacity {
+            unsafe { core::ptr::write_volatile(base.add(offset), 0) };
+        }
+        unsafe {
+            libc::munlock(base as _, self.capacity);
+            libc::munmap(base as _, self.capacity);
+        }
+    }
+}
+
+impl Deref for LockedBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for LockedBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl AsRef<[u8]> for LockedBuffer {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl AsMut<[u8]> for LockedBuffer {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+impl fmt::Debug for LockedBuffer {
+    /// The contents are never printed
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LockedBuffer")
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyRing;
+    use alloc::format;
+
+    #[test]
+    fn test_locked_buffer() {
+        let mut buffer = LockedBuffer::new(10).unwrap();
+        assert_eq!(buffer.len(), 10);
+        assert_eq!(&buffer[..], &[0u8; 10]);
+
+        // Writes are visible, and truncation only shrinks
+        buffer.copy_from_slice(b"0123456789");
+        buffer.truncate(4);
+        buffer.truncate(8);
+        assert_eq!(&buffer[..], b"0123");
+
+        // Contents are not exposed by Debug
+        assert_eq!(format!("{:?}", buffer), "LockedBuffer { len: 4, .. }");
+
+        // Empty buffers are supported
+        let buffer = LockedBuffer::new(0).unwrap();
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_locked_buffer_payload() {
+        KeyRing::with_new_session(|ring| {
+            let mut payload = LockedBuffer::new(5000).unwrap();
+            payload.fill(0x42);
+
+            // Locked buffers can be written and read
+            let key = ring.add_key("test_locked_payload", &payload).unwrap();
+            let secret = key.read_locked().unwrap();
+            assert_eq!(&secret[..], &payload[..]);
+
+            payload[..5].copy_from_slice(b"12345");
+            payload.truncate(5);
+            key.update(&payload).unwrap();
+            assert_eq!(&key.read_locked().unwrap()[..], b"12345");
+
+            let mut buffer = LockedBuffer::new(16).unwrap();
+            let len = key.read(&mut buffer).unwrap();
+            assert_eq!(&buffer[..len], b"12345");
+
+            // Cleanup
+            key.invalidate().unwrap();
+        })
+        .unwrap();
+    }
+}
@@ -1,5 +1,5 @@
 use crate::ffi::{self, KeyCtlOperation, KeySerialId};
-use crate::utils::{CStr, String};
+use crate::utils::{CStr, String, Vec};
 use crate::{KeyError, KeyPermissions, KeyType};
 use alloc::string::ToString;
 use core::str::{self, FromStr};
@@ -14,6 +14,7 @@ pub struct Metadata {
     gid: u32,
     perm: KeyPermissions,
     description: String,
+    security_label: Option<String>,
 }
 
 impl FromStr for Metadata {
@@ -29,8 +30,9 @@ impl FromStr for Metadata {
     /// In the above, type and description are strings, uid and gid are
     /// decimal strings, and perm is a hexadecimal permissions mask.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        // Begin parsing
-        let mut iter = s.split(';');
+        // Begin parsing, the description itself may contain ';' so
+        // only split off the first four fields
+        let mut iter = s.splitn(5, ';');
 
         // Parse type into KeyType
         let ktype: KeyType = iter
@@ -56,7 +58,7 @@ impl FromStr for Metadata {
             .and_then(|v| u32::from_str_radix(v, 16).ok())
             .ok_or(KeyError::InvalidDescription)?;
 
-        // Copy the actual description
+        // Copy the remainder as the actual description
         let description = iter.next().ok_or(KeyError::InvalidDescription)?.to_string();
 
         // Create the description
@@ -66,6 +68,7 @@ impl FromStr for Metadata {
             gid,
             perm: KeyPermissions::from_u32(perms),
             description,
+            security_label: None,
         })
     }
 }
@@ -74,27 +77,41 @@ impl Metadata {
     /// Internal method to derive information from an
     /// arbitrary node based on ID alone.
     pub(crate) fn from_id(id: KeySerialId) -> Result<Self, KeyError> {
-        let mut result = alloc::vec![0u8; 512];
-
         // Obtain the description from the kernel
-        let len = ffi::keyctl!(
-            KeyCtlOperation::Describe,
-            id.as_raw_id() as libc::c_ulong,
-            result.as_mut_ptr() as _,
-            result.len() as _
-        )? as usize;
+        let result: Vec<u8> = ffi::keyctl_read_alloc(KeyCtlOperation::Describe, id)?;
 
         // Construct the CStr first to remove the null terminator
-        let cs = CStr::from_bytes_with_nul(&result[..len]).or(Err(KeyError::InvalidDescription))?;
+        let cs = CStr::from_bytes_with_nul(&result).or(Err(KeyError::InvalidDescription))?;
 
         // Construct the string from the resulting data ensuring utf8 compat
         let s = cs.to_str().or(Err(KeyError::InvalidDescription))?;
         Self::from_str(s)
     }
 
+    /// Internal method to derive information from an arbitrary node,
+    /// including its LSM security label.
+    pub(crate) fn from_id_with_label(id: KeySerialId) -> Result<Self, KeyError> {
+        let mut metadata = Self::from_id(id)?;
+
+        // The label is empty when no LSM is active
+        let label = Self::security_label(id)?;
+        metadata.security_label = (!label.is_empty()).then_some(label);
+        Ok(metadata)
+    }
+
+    /// Internal method to obtain the LSM security label of a node
+    pub(crate) fn security_label(id: KeySerialId) -> Result<String, KeyError> {
+        let result: Vec<u8> = ffi::keyctl_read_alloc(KeyCtlOperation::GetSecurityLabel, id)?;
+
+        // The label is null terminated, and empty without an LSM
+        let cs = CStr::from_bytes_until_nul(&result).or(Err(KeyError::InvalidDescription))?;
+        let label = cs.to_str().or(Err(KeyError::InvalidDescription))?;
+        Ok(label.to_string())
+    }
+
     /// The type of this entry
     pub fn get_type(&self) -> KeyType {
-        self.ktype
+        self.ktype.clone()
     }
 
     /// The owning UID of this entry
@@ -116,4 +133,90 @@ impl Metadata {
     pub fn get_description(&self) -> &str {
         &self.description
     }
+
+    /// The LSM security label of this entry, if the running LSM attached
+    /// one. Only obtained by [Key::metadata_with_label](crate::Key::metadata_with_label)
+    /// or [KeyRing::metadata_with_label](crate::KeyRing::metadata_with_label).
+    pub fn get_security_label(&self) -> Option<&str> {
+        self.security_label.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyRing;
+    use alloc::format;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_parse_semicolon_description() {
+        let info = Metadata::from_str("user;1000;1000;3f010000;a;b;c").unwrap();
+        assert_eq!(info.get_type(), KeyType::User);
+        assert_eq!(info.get_uid(), 1000);
+        assert_eq!(info.get_gid(), 1000);
+        assert_eq!(info.get_perms().bits(), 0x3f010000);
+        assert_eq!(info.get_description(), "a;b;c");
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for s in [
+            "",
+            "user",
+            "user;1000;1000;3f010000",
+            "user;x;1000;3f010000;desc",
+            "user;1000;x;3f010000;desc",
+            "user;1000;1000;zz;desc",
+            ";1000;1000;3f010000;desc",
+        ] {
+            assert_eq!(
+                Metadata::from_str(s).unwrap_err(),
+                KeyError::InvalidDescription
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_id_long_description() {
+        // Longer than any fixed buffer and containing the separator
+        let description = format!("long;{}", "x".repeat(4000));
+
+        // Run within a fresh session keyring
+        KeyRing::with_new_session(|ring| {
+            let key = ring.add_key(&description, b"data").unwrap();
+
+            let info = key.metadata().unwrap();
+            assert_eq!(info.get_type(), KeyType::User);
+            assert_eq!(info.get_description(), description);
+            key.invalidate().unwrap();
+        })
+        .unwrap();
+    }
+
+    proptest! {
+        #[test]
+        fn prop_parse_roundtrip(
+            ktype in "[a-z_.]{1,16}",
+            uid: u32,
+            gid: u32,
+            perm: u32,
+            description in "\\PC*",
+        ) {
+            let s = format!("{};{};{};{:08x};{}", ktype, uid, gid, perm, description);
+            let info = Metadata::from_str(&s).unwrap();
+            let parsed = info.get_type();
+            prop_assert_eq!(parsed.as_cstr().to_str().unwrap(), ktype);
+            prop_assert_eq!(info.get_uid(), uid);
+            prop_assert_eq!(info.get_gid(), gid);
+            prop_assert_eq!(info.get_perms().bits(), perm);
+            prop_assert_eq!(info.get_description(), description);
+        }
+
+        #[test]
+        fn prop_parse_arbitrary(s in "\\PC*") {
+            // Arbitrary input must never panic
+            let _ = Metadata::from_str(&s);
+        }
+    }
 }
@@ -0,0 +1,212 @@
+//! Owned handles that clean up keys and keyrings once dropped
+//!
+use crate::{Key, KeyRing};
+use core::mem::ManuallyDrop;
+use core::ops::Deref;
+
+/// What happens to an [OwnedKey] or [OwnedKeyRing] once it is dropped.
+///
+/// Errors while cleaning up are ignored, e.g. when the key was already
+/// removed by other means.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DropAction {
+    /// Invalidate the node, removing it from all keyrings, see [Key::invalidate]
+    #[default]
+    Invalidate,
+    /// Revoke the node, see [Key::revoke]
+    Revoke,
+    /// Unlink the node from the provided keyring, it is garbage collected
+    /// once no other keyring links to it
+    Unlink(KeyRing),
+    /// Leave the node as is
+    Leak,
+}
+
+/// A [Key] that is cleaned up according to its [DropAction] once dropped.
+///
+/// Dereferences to [Key], so all key operations are available on it.
+///
+/// Usage:
+///
+/// ```
+/// use linux_keyutils::{DropAction, KeyError, KeyRing, KeyRingIdentifier, OwnedKey};
+///
+/// fn use_ephemeral_secret() -> Result<(), KeyError> {
+///     let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false)?;
+///
+///     // The key is invalidated when this scope exits, even on error
+///     let key = OwnedKey::new(ring.add_key("ephemeral", b"secret")?, DropAction::Invalidate);
+///     let secret = key.read_to_vec()?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, PartialEq, Eq)]
+pub struct OwnedKey {
+    key: Key,
+    action: DropAction,
+}
+
+/// A [KeyRing] that is cleaned up according to its [DropAction] once dropped.
+///
+/// Dereferences to [KeyRing], so all keyring operations are available on it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OwnedKeyRing {
+    ring: KeyRing,
+    action: DropAction,
+}
+
+impl OwnedKey {
+    /// Take ownership of a key, cleaning it up with the provided action
+    pub fn new(key: Key, action: DropAction) -> Self {
+        Self { key, action }
+    }
+
+    /// The action performed when this key is dropped
+    pub fn get_action(&self) -> DropAction {
+        self.action
+    }
+
+    /// Change the action performed when this key is dropped
+    pub fn set_action(&mut self, action: DropAction) {
+        self.action = action;
+    }
+
+    /// Release ownership of the key without cleaning it up
+    pub fn into_inner(self) -> Key {
+        ManuallyDrop::new(self).key
+    }
+}
+
+impl OwnedKeyRing {
+    /// Take ownership of a keyring, cleaning it up with the provided action
+    pub fn new(ring: KeyRing, action: DropAction) -> Self {
+        Self { ring, action }
+    }
+
+    /// The action performed when this keyring is dropped
+    pub fn get_action(&self) -> DropAction {
+        self.action
+    }
+
+    /// Change the action performed when this keyring is dropped
+    pub fn set_action(&mut self, action: DropAction) {
+        self.action = action;
+    }
+
+    /// Release ownership of the keyring without cleaning it up
+    pub fn into_inner(self) -> KeyRing {
+        ManuallyDrop::new(self).ring
+    }
+}
+
+impl From<Key> for OwnedKey {
+    /// Take ownership of a key, invalidating it once dropped
+    fn from(key: Key) -> Self {
+        Self::new(key, DropAction::default())
+    }
+}
+
+impl From<KeyRing> for OwnedKeyRing {
+    /// Take ownership of a keyring, invalidating it once dropped
+    fn from(ring: KeyRing) -> Self {
+        Self::new(ring, DropAction::default())
+    }
+}
+
+impl Deref for OwnedKey {
+    type Target = Key;
+
+    fn deref(&self) -> &Self::Target {
+        &self.key
+    }
+}
+
+impl Deref for OwnedKeyRing {
+    type Target = KeyRing;
+
+    fn deref(&self) -> &Self::Target {
+        &self.ring
+    }
+}
+
+impl Drop for OwnedKey {
+    fn drop(&mut self) {
+        _ = match self.action {
+            DropAction::Invalidate => self.key.invalidate(),
+            DropAction::Revoke => self.key.revoke(),
+            DropAction::Unlink(ring) => ring.unlink_key(self.key),
+            DropAction::Leak => Ok(()),
+        };
+    }
+}
+
+impl Drop for OwnedKeyRing {
+    fn drop(&mut self) {
+        // Keyrings are invalidated and revoked like any other key
+        let key = Key::from_id(self.ring.get_id());
+        _ = match self.action {
+            DropAction::Invalidate => key.invalidate(),
+            DropAction::Revoke => key.revoke(),
+            DropAction::Unlink(ring) => ring.unlink_keyring(self.ring),
+            DropAction::Leak => Ok(()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyError;
+
+    #[test]
+    fn test_owned_key_actions() {
+        KeyRing::with_new_session(|ring| {
+            // Invalidated keys disappear
+            let key = ring.add_key("owned_invalidate", b"data").unwrap();
+            drop(OwnedKey::from(key));
+            assert_eq!(key.metadata().unwrap_err(), KeyError::KeyDoesNotExist);
+
+            // Revoked keys remain until garbage collected but are unusable
+            let key = ring.add_key("owned_revoke", b"data").unwrap();
+            drop(OwnedKey::new(key, DropAction::Revoke));
+            assert_eq!(key.read_to_vec().unwrap_err(), KeyError::KeyRevoked);
+
+            // Unlinked keys are no longer found in the keyring
+            let key = ring.add_key("owned_unlink", b"data").unwrap();
+            drop(OwnedKey::new(key, DropAction::Unlink(ring)));
+            assert!(!ring.get_links().unwrap().contains(&key));
+
+            // Leaked keys are untouched, as are released keys
+            let key = ring.add_key("owned_leak", b"data").unwrap();
+            drop(OwnedKey::new(key, DropAction::Leak));
+            assert_eq!(key.read_to_vec().unwrap(), b"data");
+            let owned = OwnedKey::from(key);
+            assert_eq!(owned.get_action(), DropAction::Invalidate);
+            assert_eq!(owned.into_inner(), key);
+            assert_eq!(key.read_to_vec().unwrap(), b"data");
+            key.invalidate().unwrap();
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_owned_keyring_actions() {
+        KeyRing::with_new_session(|ring| {
+            // Keyrings are invalidated by default
+            let child = ring.add_keyring("owned_ring_invalidate").unwrap();
+            {
+                let owned = OwnedKeyRing::from(child);
+                owned.add_key("owned_ring_key", b"data").unwrap();
+            }
+            assert_eq!(child.metadata().unwrap_err(), KeyError::KeyDoesNotExist);
+
+            // Or unlinked from their parent
+            let child = ring.add_keyring("owned_ring_unlink").unwrap();
+            let mut owned = OwnedKeyRing::new(child, DropAction::Leak);
+            owned.set_action(DropAction::Unlink(ring));
+            drop(owned);
+            assert!(!ring.get_links().unwrap().contains(&child));
+        })
+        .unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! Types used by the public key operations of [Key](crate::Key)
+//!
+use crate::ffi::KeyCtlPkeyQuery;
+use crate::utils::{CString, String};
+use crate::KeyError;
+use alloc::format;
+use bitflags::bitflags;
+
+/// Encoding scheme applied to the data of a public key operation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PkeyEncoding {
+    /// No encoding, the data is used as is
+    Raw,
+    /// RSASSA-PKCS1-v1_5 or RSAES-PKCS1-v1_5
+    Pkcs1,
+    /// ANSI X9.62 encoded ECDSA signatures
+    X962,
+}
+
+/// The info string passed to the public key operations.
+///
+/// Usage:
+///
+/// ```
+/// use linux_keyutils::{PkeyEncoding, PkeyInfo};
+///
+/// // Equivalent to "enc=pkcs1 hash=sha256"
+/// let info = PkeyInfo::new()
+///     .encoding(PkeyEncoding::Pkcs1)
+///     .hash("sha256");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PkeyInfo {
+    encoding: Option<PkeyEncoding>,
+    hash: Option<String>,
+}
+
+bitflags! {
+    /// Operations supported by an asymmetric key, returned by
+    /// [PkeyQuery::get_supported_ops].
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[repr(transparent)]
+    pub struct PkeyOperations: u32 {
+        /// The key may be used to encrypt data
+        const ENCRYPT = 0x01;
+        /// The key may be used to decrypt data
+        const DECRYPT = 0x02;
+        /// The key may be used to create signatures
+        const SIGN = 0x04;
+        /// The key may be used to verify signatures
+        const VERIFY = 0x08;
+    }
+}
+
+/// Parameters of an asymmetric key, returned by
+/// [Key::pkey_query](crate::Key::pkey_query)
+#[derive(Debug, Copy, Clone)]
+pub struct PkeyQuery {
+    supported_ops: PkeyOperations,
+    key_size: u32,
+    max_data_size: u16,
+    max_sig_size: u16,
+    max_enc_size: u16,
+    max_dec_size: u16,
+}
+
+impl PkeyEncoding {
+    /// The name of this encoding as understood by the kernel
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Raw => "raw",
+            Self::Pkcs1 => "pkcs1",
+            Self::X962 => "x962",
+        }
+    }
+}
+
+impl PkeyInfo {
+    /// Create a new PkeyInfo, using the kernel defaults for every parameter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the encoding scheme applied to the data
+    pub fn encoding(mut self, encoding: PkeyEncoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Set the name of the hash algorithm used to create the digest
+    /// that is being signed or verified, i.e. `sha256`.
+    pub fn hash<H: AsRef<str> + ?Sized>(mut self, hash: &H) -> Self {
+        self.hash = Some(hash.as_ref().into());
+        self
+    }
+
+    /// Internal method to construct the info string passed to the kernel
+    pub(crate) fn to_cstring(&self) -> Result<CString, KeyError> {
+        let mut info = String::new();
+        if let Some(encoding) = self.encoding {
+            info.push_str(&format!("enc={}", encoding.as_str()));
+        }
+        if let Some(hash) = &self.hash {
+            if !info.is_empty() {
+                info.push(' ');
+            }
+            info.push_str(&format!("hash={}", hash));
+        }
+        CString::new(info).or(Err(KeyError::InvalidArguments))
+    }
+}
+
+impl From<KeyCtlPkeyQuery> for PkeyQuery {
+    fn from(raw: KeyCtlPkeyQuery) -> Self {
+        Self {
+            supported_ops: PkeyOperations::from_bits_retain(raw.supported_ops),
+            key_size: raw.key_size,
+            max_data_size: raw.max_data_size,
+            max_sig_size: raw.max_sig_size,
+            max_enc_size: raw.max_enc_size,
+            max_dec_size: raw.max_dec_size,
+        }
+    }
+}
+
+impl PkeyQuery {
+    /// The operations supported by the key
+    pub fn get_supported_ops(&self) -> PkeyOperations {
+        self.supported_ops
+    }
+
+    /// The size of the key in bits
+    pub fn get_key_size(&self) -> u32 {
+        self.key_size
+    }
+
+    /// The maximum size of the data that can be signed in bytes
+    pub fn get_max_data_size(&self) -> usize {
+        self.max_data_size as usize
+    }
+
+    /// The maximum size of a signature in bytes
+    pub fn get_max_sig_size(&self) -> usize {
+        self.max_sig_size as usize
+    }
+
+    /// The maximum size of an encrypted blob in bytes
+    pub fn get_max_enc_size(&self) -> usize {
+        self.max_enc_size as usize
+    }
+
+    /// The maximum size of a decrypted blob in bytes
+    pub fn get_max_dec_size(&self) -> usize {
+        self.max_dec_size as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info_string() {
+        let info = PkeyInfo::new();
+        assert_eq!(info.to_cstring().unwrap().to_str().unwrap(), "");
+
+        let info = PkeyInfo::new().encoding(PkeyEncoding::Pkcs1);
+        assert_eq!(info.to_cstring().unwrap().to_str().unwrap(), "enc=pkcs1");
+
+        let info = PkeyInfo::new().hash("sha256");
+        assert_eq!(info.to_cstring().unwrap().to_str().unwrap(), "hash=sha256");
+
+        let info = PkeyInfo::new().encoding(PkeyEncoding::Raw).hash("sha1");
+        assert_eq!(
+            info.to_cstring().unwrap().to_str().unwrap(),
+            "enc=raw hash=sha1"
+        );
+
+        let info = PkeyInfo::new().hash("bad\0hash");
+        assert_eq!(info.to_cstring().unwrap_err(), KeyError::InvalidArguments);
+    }
+}
@@ -0,0 +1,256 @@
+//! Framework for writing request-key(8) helpers.
+//!
+//! When a key that does not exist is requested with callout information
+//! (see [KeyRing::request_key]), the kernel creates an uninstantiated key and
+//! invokes `/sbin/request-key` to construct it. The helper is passed the
+//! following arguments:
+//!
+//! `<op> <key> <uid> <gid> <thread keyring> <process keyring> <session keyring>`
+//!
+//! This module takes care of parsing those arguments, assuming authority over
+//! the key and reading the callout information. So a helper only has to
+//! implement [Handler] to decide the [Outcome] of each request.
+//!
+//! Usage:
+//!
+//! ```no_run
+//! use linux_keyutils::request_key::{self, Handler, Outcome, Request};
+//!
+//! struct Echo;
+//!
+//! impl Handler for Echo {
+//!     // Use the callout information as the payload of the key
+//!     fn handle(&mut self, request: &Request) -> Outcome {
+//!         Outcome::Instantiate {
+//!             payload: request.get_callout_info().to_vec(),
+//!             link: Some(request.get_session_keyring()),
+//!         }
+//!     }
+//! }
+//!
+//! fn main() -> Result<(), linux_keyutils::KeyError> {
+//!     request_key::run(&mut Echo, std::env::args().skip(1))
+//! }
+//! ```
+//!
+//! See <https://www.kernel.org/doc/html/latest/security/keys/request-key.html>
+use crate::utils::{String, Vec};
+use crate::{Key, KeyError, KeyRing, KeyRingIdentifier, KeySerialId};
+use alloc::string::ToString;
+
+/// A request to construct a key, as passed to a helper by the kernel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    operation: String,
+    key: Key,
+    uid: u32,
+    gid: u32,
+    thread: Option<KeyRing>,
+    process: Option<KeyRing>,
+    session: KeyRing,
+    callout_info: Vec<u8>,
+}
+
+/// The decision of a [Handler] on how to complete a [Request].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Instantiate the key with the provided payload, optionally also
+    /// linking it into a keyring.
+    Instantiate {
+        /// The payload of the key
+        payload: Vec<u8>,
+        /// Keyring to link the instantiated key into
+        link: Option<KeyRing>,
+    },
+    /// Negatively instantiate the key, so that further requests for it fail
+    /// with [KeyError::KeyDoesNotExist] until the timeout (in seconds) expires.
+    Negate {
+        /// Seconds until the negative key expires
+        timeout: usize,
+    },
+    /// Reject the key, so that further requests for it fail with the
+    /// provided error until the timeout (in seconds) expires.
+    Reject {
+        /// Seconds until the rejected key expires
+        timeout: usize,
+        /// Error reported to the requesters, such as [KeyError::KeyRejected]
+        error: KeyError,
+    },
+}
+
+/// Decides how to construct the keys requested from a helper.
+pub trait Handler {
+    /// Construct the key for the provided request.
+    fn handle(&mut self, request: &Request) -> Outcome;
+}
+
+impl Request {
+    /// Parse the arguments passed to the helper by the kernel, excluding the
+    /// program name.
+    ///
+    /// Keyring IDs of 0 indicate that the requesting thread has no such
+    /// keyring. The callout information is not available until authority
+    /// over the key is assumed, see [run].
+    pub fn from_args<I, S>(args: I) -> Result<Self, KeyError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut args = args.into_iter();
+        let mut next = || args.next().ok_or(KeyError::InvalidArguments);
+
+        // Operation name, the kernel only uses "create"
+        let operation = next()?.as_ref().to_string();
+
+        // Numeric arguments
+        let key = Key::from_id(parse_id(next()?.as_ref())?);
+        let uid = next()?
+            .as_ref()
+            .parse()
+            .or(Err(KeyError::InvalidArguments))?;
+        let gid = next()?
+            .as_ref()
+            .parse()
+            .or(Err(KeyError::InvalidArguments))?;
+
+        // The keyrings of the requesting thread
+        let thread = parse_keyring(next()?.as_ref())?;
+        let process = parse_keyring(next()?.as_ref())?;
+        let session = parse_keyring(next()?.as_ref())?.ok_or(KeyError::InvalidArguments)?;
+
+        Ok(Self {
+            operation,
+            key,
+            uid,
+            gid,
+            thread,
+            process,
+            session,
+            callout_info: Vec::new(),
+        })
+    }
+
+    /// The requested operation, "create" for key construction
+    pub fn get_operation(&self) -> &str {
+        &self.operation
+    }
+
+    /// The uninstantiated key to construct
+    pub fn get_key(&self) -> Key {
+        self.key
+    }
+
+    /// The filesystem UID of the requesting process
+    pub fn get_uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// The filesystem GID of the requesting process
+    pub fn get_gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// The thread keyring of the requesting thread, if any
+    pub fn get_thread_keyring(&self) -> Option<KeyRing> {
+        self.thread
+    }
+
+    /// The process keyring of the requesting process, if any
+    pub fn get_process_keyring(&self) -> Option<KeyRing> {
+        self.process
+    }
+
+    /// The session keyring of the requesting process
+    pub fn get_session_keyring(&self) -> KeyRing {
+        self.session
+    }
+
+    /// The callout information provided by the requester
+    pub fn get_callout_info(&self) -> &[u8] {
+        &self.callout_info
+    }
+}
+
+/// Run a helper for the provided arguments, excluding the program name.
+///
+/// Assumes authority over the requested key, reads the callout information
+/// from the authorization key and then completes the request with the
+/// [Outcome] decided by the handler. Should this fail before the key is
+/// instantiated, the kernel negates the key once the helper exits.
+pub fn run<H, I, S>(handler: &mut H, args: I) -> Result<(), KeyError>
+where
+    H: Handler + ?Sized,
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut request = Request::from_args(args)?;
+
+    // Assume authority over the key, making the authorization key
+    // available through its special ID
+    request.key.assume_authority()?;
+
+    // The payload of the authorization key is the callout information
+    let auth = Key::from_id(KeySerialId::new(KeyRingIdentifier::ReqKeyAuthKey as i32));
+    request.callout_info = auth.read_to_vec()?;
+
+    match handler.handle(&request) {
+        Outcome::Instantiate { payload, link } => {
+            let dest = link.map_or(KeySerialId::new(0), |ring| ring.get_id());
+            request.key.instantiate(&payload, dest)
+        }
+        Outcome::Negate { timeout } => request.key.negate(timeout),
+        Outcome::Reject { timeout, error } => request.key.reject(timeout, error),
+    }
+}
+
+/// Internal method to parse a decimal key ID
+fn parse_id(arg: &str) -> Result<KeySerialId, KeyError> {
+    let id: i32 = arg.parse().or(Err(KeyError::InvalidArguments))?;
+    Ok(KeySerialId::new(id))
+}
+
+/// Internal method to parse a keyring ID, where 0 indicates no keyring
+fn parse_keyring(arg: &str) -> Result<Option<KeyRing>, KeyError> {
+    let id = parse_id(arg)?;
+    Ok((id.as_raw_id() != 0).then(|| KeyRing::from_id(id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_args() {
+        let args = ["create", "123", "1000", "100", "0", "456", "789"];
+        let request = Request::from_args(args).unwrap();
+        assert_eq!(request.get_operation(), "create");
+        assert_eq!(request.get_key(), Key::from_id(KeySerialId::new(123)));
+        assert_eq!(request.get_uid(), 1000);
+        assert_eq!(request.get_gid(), 100);
+        assert_eq!(request.get_thread_keyring(), None);
+        assert_eq!(
+            request.get_process_keyring(),
+            Some(KeyRing::from_id(KeySerialId::new(456)))
+        );
+        assert_eq!(
+            request.get_session_keyring(),
+            KeyRing::from_id(KeySerialId::new(789))
+        );
+        assert!(request.get_callout_info().is_empty());
+    }
+
+    #[test]
+    fn test_from_args_invalid() {
+        // Missing arguments
+        let result = Request::from_args(["create", "123", "1000", "100", "0", "456"]);
+        assert_eq!(result.unwrap_err(), KeyError::InvalidArguments);
+
+        // Non-numeric arguments
+        let result = Request::from_args(["create", "key", "1000", "100", "0", "456", "789"]);
+        assert_eq!(result.unwrap_err(), KeyError::InvalidArguments);
+
+        // A session keyring is always provided
+        let result = Request::from_args(["create", "123", "1000", "100", "0", "456", "0"]);
+        assert_eq!(result.unwrap_err(), KeyError::InvalidArguments);
+    }
+}
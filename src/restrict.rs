@@ -0,0 +1,111 @@
+//! Restriction schemes for [KeyRing::restrict](crate::KeyRing::restrict)
+//!
+use crate::utils::{CString, String};
+use crate::{KeyError, KeySerialId};
+use alloc::format;
+
+/// Restriction schemes limiting which keys may be linked to a keyring.
+/// See `man 3 keyctl_restrict_keyring`.
+///
+/// These schemes are understood by the [KeyType::Asymmetric](crate::KeyType::Asymmetric)
+/// key type, and only allow keys that are signed by a trusted key to be linked.
+///
+/// Usage:
+///
+/// ```
+/// use linux_keyutils::{KeySerialId, Restriction};
+///
+/// // Only allow keys signed by the key or keyring 1234, or by a key
+/// // already linked to the restricted keyring
+/// let restriction = Restriction::KeyOrKeyring {
+///     id: Some(KeySerialId::new(1234)),
+///     chain: true,
+/// };
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Restriction {
+    /// Only allow keys signed by a key on the builtin trusted keyring
+    BuiltinTrusted,
+    /// Only allow keys signed by a key on the builtin or secondary trusted keyrings
+    BuiltinAndSecondaryTrusted,
+    /// Only allow keys signed by the provided key, or by a key linked to the
+    /// provided keyring. If chain is set, keys signed by a key already linked
+    /// to the restricted keyring are allowed as well. The ID may only be
+    /// omitted when chaining.
+    KeyOrKeyring {
+        /// The signing key or keyring
+        id: Option<KeySerialId>,
+        /// Also allow keys signed by keys in the restricted keyring
+        chain: bool,
+    },
+}
+
+impl Restriction {
+    /// Internal method to construct the restriction string passed to the kernel
+    pub(crate) fn to_cstring(self) -> Result<CString, KeyError> {
+        let restriction: String = match self {
+            Self::BuiltinTrusted => "builtin_trusted".into(),
+            Self::BuiltinAndSecondaryTrusted => "builtin_and_secondary_trusted".into(),
+            Self::KeyOrKeyring {
+                id: None,
+                chain: false,
+            } => return Err(KeyError::InvalidArguments),
+            Self::KeyOrKeyring { id, chain } => format!(
+                "key_or_keyring:{}{}",
+                id.map_or(0, |id| id.as_raw_id()),
+                if chain { ":chain" } else { "" }
+            ),
+        };
+        CString::new(restriction).or(Err(KeyError::InvalidArguments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restriction_string() {
+        for (restriction, expected) in [
+            (Restriction::BuiltinTrusted, "builtin_trusted"),
+            (
+                Restriction::BuiltinAndSecondaryTrusted,
+                "builtin_and_secondary_trusted",
+            ),
+            (
+                Restriction::KeyOrKeyring {
+                    id: Some(KeySerialId::new(1234)),
+                    chain: false,
+                },
+                "key_or_keyring:1234",
+            ),
+            (
+                Restriction::KeyOrKeyring {
+                    id: Some(KeySerialId::new(1234)),
+                    chain: true,
+                },
+                "key_or_keyring:1234:chain",
+            ),
+            (
+                Restriction::KeyOrKeyring {
+                    id: None,
+                    chain: true,
+                },
+                "key_or_keyring:0:chain",
+            ),
+        ] {
+            let value = restriction.to_cstring().unwrap();
+            assert_eq!(value.to_str().unwrap(), expected);
+        }
+
+        // The ID may only be omitted when chaining
+        let restriction = Restriction::KeyOrKeyring {
+            id: None,
+            chain: false,
+        };
+        assert_eq!(
+            restriction.to_cstring().unwrap_err(),
+            KeyError::InvalidArguments
+        );
+    }
+}
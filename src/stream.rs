@@ -0,0 +1,151 @@
+//! Key change notifications as an async stream, registered with the
+//! tokio reactor.
+//!
+use crate::{Key, KeyError, KeyNotification, KeyRing, KeyWatcher};
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use futures_core::Stream;
+use tokio::io::unix::AsyncFd;
+
+/// An async [Stream] of notifications for watched keys and keyrings.
+///
+/// Behaves like [KeyWatcher], except that the notification pipe is non-blocking
+/// and registered with the tokio reactor. Must be created from within a tokio
+/// runtime.
+///
+/// Usage:
+///
+/// ```no_run
+/// use linux_keyutils::{KeyError, KeyEventStream, KeyRing, KeyRingIdentifier};
+///
+/// async fn next_change() -> Result<(), KeyError> {
+///     let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false)?;
+///
+///     // Watch the session keyring for changes
+///     let mut events = KeyEventStream::new(32)?;
+///     events.watch_keyring(ring, 1)?;
+///
+///     // Wait for the next change without blocking the runtime
+///     let notification = events.recv().await?;
+///     println!("{:?}", notification.get_event());
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct KeyEventStream {
+    inner: AsyncFd<KeyWatcher>,
+}
+
+impl KeyEventStream {
+    /// Create a new notification pipe able to queue up to `queue_size`
+    /// notifications, and register it with the current tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics when called outside of a tokio runtime, or within a runtime
+    /// that does not have IO enabled.
+    pub fn new(queue_size: usize) -> Result<Self, KeyError> {
+        let watcher = KeyWatcher::with_flags(queue_size, libc::O_NONBLOCK)?;
+        let inner = AsyncFd::new(watcher)
+            .map_err(|e| KeyError::from_code(e.raw_os_error().unwrap_or(libc::EIO)))?;
+        Ok(Self { inner })
+    }
+
+    /// Watch a key for changes. See [KeyWatcher::watch_key].
+    pub fn watch_key(&self, key: Key, watch_id: u8) -> Result<(), KeyError> {
+        self.inner.get_ref().watch_key(key, watch_id)
+    }
+
+    /// Watch a keyring for changes. See [KeyWatcher::watch_keyring].
+    pub fn watch_keyring(&self, keyring: KeyRing, watch_id: u8) -> Result<(), KeyError> {
+        self.inner.get_ref().watch_keyring(keyring, watch_id)
+    }
+
+    /// Remove the watch on a key.
+    pub fn unwatch_key(&self, key: Key) -> Result<(), KeyError> {
+        self.inner.get_ref().unwatch_key(key)
+    }
+
+    /// Remove the watch on a keyring.
+    pub fn unwatch_keyring(&self, keyring: KeyRing) -> Result<(), KeyError> {
+        self.inner.get_ref().unwatch_keyring(keyring)
+    }
+
+    /// Wait for the next notification. Equivalent to polling the stream
+    /// directly, without requiring a `StreamExt` trait.
+    pub async fn recv(&mut self) -> Result<KeyNotification, KeyError> {
+        core::future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Internal method to poll for the next notification
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<KeyNotification, KeyError>> {
+        loop {
+            if let Some(notification) = self.inner.get_mut().next_pending() {
+                return Poll::Ready(Ok(notification));
+            }
+
+            let mut guard = match ready!(self.inner.poll_read_ready_mut(cx)) {
+                Ok(guard) => guard,
+                Err(e) => {
+                    let code = e.raw_os_error().unwrap_or(libc::EIO);
+                    return Poll::Ready(Err(KeyError::from_code(code)));
+                }
+            };
+
+            // Drained the pipe, wait for the reactor to signal readiness
+            match guard.get_inner_mut().fill() {
+                Ok(()) => continue,
+                Err(KeyError::Unknown(libc::EAGAIN)) => guard.clear_ready(),
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+    }
+}
+
+impl Stream for KeyEventStream {
+    type Item = Result<KeyNotification, KeyError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_recv(cx).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyEvent;
+
+    #[test]
+    fn test_stream_keyring() {
+        KeyRing::with_new_session(|ring| {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .build()
+                .unwrap();
+
+            runtime.block_on(async {
+                // Skip kernels without notification support
+                let mut events = match KeyEventStream::new(16) {
+                    Err(KeyError::OperationNotSupported) => return,
+                    result => result.unwrap(),
+                };
+                match events.watch_keyring(ring, 1) {
+                    Err(KeyError::OperationNotSupported) => return,
+                    result => result.unwrap(),
+                }
+
+                let key = ring.add_key("test_stream_key", b"data").unwrap();
+                let notification = events.recv().await.unwrap();
+                assert_eq!(notification.get_watch_id(), 1);
+                assert_eq!(
+                    notification.get_event(),
+                    KeyEvent::Linked {
+                        keyring: ring.get_id(),
+                        key: key.get_id(),
+                    }
+                );
+            });
+        })
+        .unwrap();
+    }
+}
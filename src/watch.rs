@@ -0,0 +1,439 @@
+//! Key change notifications delivered through a notification pipe
+//!
+use crate::ffi::{self, KeyCtlOperation, KeyNotificationSubtype};
+use crate::{Key, KeyError, KeyRing, KeySerialId};
+use alloc::collections::VecDeque;
+
+/// Size of a `struct watch_notification` header
+const HEADER_LEN: usize = 8;
+
+/// Size of a `struct key_notification`
+const KEY_NOTIFICATION_LEN: usize = 16;
+
+/// A change to a watched key or keyring.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyEvent {
+    /// The key was instantiated
+    Instantiated(KeySerialId),
+    /// The payload of the key was updated
+    Updated(KeySerialId),
+    /// A key was linked to the watched keyring
+    Linked {
+        /// The watched keyring
+        keyring: KeySerialId,
+        /// The key that was linked
+        key: KeySerialId,
+    },
+    /// A key was unlinked from the watched keyring
+    Unlinked {
+        /// The watched keyring
+        keyring: KeySerialId,
+        /// The key that was unlinked
+        key: KeySerialId,
+    },
+    /// The watched keyring was cleared
+    Cleared(KeySerialId),
+    /// The key was revoked
+    Revoked(KeySerialId),
+    /// The key was invalidated
+    Invalidated(KeySerialId),
+    /// The attributes of the key, such as its permissions, were changed
+    SetAttr(KeySerialId),
+    /// The watched key was destroyed, no further events follow for this watch
+    WatchRemoved,
+    /// Events were lost because the notification queue overflowed
+    Lost,
+}
+
+/// A notification read from a [KeyWatcher].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyNotification {
+    watch_id: u8,
+    event: KeyEvent,
+}
+
+/// Watches keys and keyrings for changes. See `man 2 keyctl_watch_key`.
+///
+/// Notifications are delivered by the kernel through a notification pipe,
+/// which requires a kernel built with `CONFIG_KEY_NOTIFICATIONS`. The watch
+/// ID chosen when adding a watch is reported alongside every event, and
+/// allows the caller to tell watches apart.
+///
+/// Usage:
+///
+/// ```no_run
+/// use linux_keyutils::{KeyError, KeyEvent, KeyRing, KeyRingIdentifier, KeyWatcher};
+///
+/// fn wait_for_rotation() -> Result<(), KeyError> {
+///     let ring = KeyRing::from_special_id(KeyRingIdentifier::Session, false)?;
+///     let key = ring.search("my-secret")?;
+///
+///     // Watch the key for changes
+///     let mut watcher = KeyWatcher::new(32)?;
+///     watcher.watch_key(key, 1)?;
+///
+///     // Block until the payload changes
+///     loop {
+///         let notification = watcher.read()?;
+///         if let KeyEvent::Updated(_) = notification.get_event() {
+///             return Ok(());
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct KeyWatcher {
+    fd: libc::c_int,
+    write_fd: libc::c_int,
+    pending: VecDeque<KeyNotification>,
+}
+
+impl KeyNotification {
+    /// The watch ID provided when the watch was added
+    pub fn get_watch_id(&self) -> u8 {
+        self.watch_id
+    }
+
+    /// The event that occurred
+    pub fn get_event(&self) -> KeyEvent {
+        self.event
+    }
+
+    /// Internal method to decode a single notification record.
+    ///
+    /// Returns None for records that are not understood.
+    pub(crate) fn decode(record: &[u8]) -> Option<Self> {
+        let word = |offset: usize| -> Option<u32> {
+            let raw = record.get(offset..offset + 4)?;
+            Some(u32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]]))
+        };
+
+        // The type and subtype share the first word as bitfields
+        let header = word(0)?;
+        #[cfg(target_endian = "little")]
+        let (wtype, subtype) = (header & 0x00ff_ffff, (header >> 24) as u8);
+        #[cfg(target_endian = "big")]
+        let (wtype, subtype) = (header >> 8, (header & 0xff) as u8);
+
+        let info = word(4)?;
+        let watch_id = ((info & ffi::WATCH_INFO_ID) >> ffi::WATCH_INFO_ID_SHIFT) as u8;
+
+        let event = match (wtype, subtype) {
+            (ffi::WATCH_TYPE_META, ffi::WATCH_META_REMOVAL_NOTIFICATION) => KeyEvent::WatchRemoved,
+            (ffi::WATCH_TYPE_META, ffi::WATCH_META_LOSS_NOTIFICATION) => KeyEvent::Lost,
+            (ffi::WATCH_TYPE_KEY_NOTIFY, subtype) => {
+                if record.len() < KEY_NOTIFICATION_LEN {
+                    return None;
+                }
+                let id = KeySerialId::new(word(8)? as i32);
+                let aux = KeySerialId::new(word(12)? as i32);
+                match subtype {
+                    x if x == KeyNotificationSubtype::Instantiated as u8 => {
+                        KeyEvent::Instantiated(id)
+                    }
+                    x if x == KeyNotificationSubtype::Updated as u8 => KeyEvent::Updated(id),
+                    x if x == KeyNotificationSubtype::Linked as u8 => KeyEvent::Linked {
+                        keyring: id,
+                        key: aux,
+                    },
+                    x if x == KeyNotificationSubtype::Unlinked as u8 => KeyEvent::Unlinked {
+                        keyring: id,
+                        key: aux,
+                    },
+                    x if x == KeyNotificationSubtype::Cleared as u8 => KeyEvent::Cleared(id),
+                    x if x == KeyNotificationSubtype::Revoked as u8 => KeyEvent::Revoked(id),
+                    x if x == KeyNotificationSubtype::Invalidated as u8 => {
+                        KeyEvent::Invalidated(id)
+                    }
+                    x if x == KeyNotificationSubtype::SetAttr as u8 => KeyEvent::SetAttr(id),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some(Self { watch_id, event })
+    }
+}
+
+impl KeyWatcher {
+    /// Create a new notification pipe able to queue up to `queue_size`
+    /// notifications. The kernel limits the queue to 512 notifications.
+    ///
+    /// Kernels built without notification support fail with
+    /// [KeyError::OperationNotSupported].
+    pub fn new(queue_size: usize) -> Result<Self, KeyError> {
+        Self::with_flags(queue_size, 0)
+    }
+
+    /// Internal constructor allowing additional pipe flags
+    pub(crate) fn with_flags(queue_size: usize, flags: libc::c_int) -> Result<Self, KeyError> {
+        let mut fds = [0 as libc::c_int; 2];
+
+        // Create the notification pipe, kernels without CONFIG_WATCH_QUEUE
+        // return ENOPKG
+        let res = unsafe {
+            libc::pipe2(
+                fds.as_mut_ptr(),
+                ffi::O_NOTIFICATION_PIPE | libc::O_CLOEXEC | flags,
+            )
+        };
+        if res < 0 {
+            return Err(match KeyError::from_errno() {
+                KeyError::Unknown(libc::ENOPKG) => KeyError::OperationNotSupported,
+                err => err,
+            });
+        }
+
+        // The write end is never written to, but is kept open since reads
+        // from a pipe without writers return EOF instead of blocking
+        let watcher = Self {
+            fd: fds[0],
+            write_fd: fds[1],
+            pending: VecDeque::new(),
+        };
+
+        // Allocate the notification slots
+        let res = unsafe {
+            libc::ioctl(
+                watcher.fd,
+                ffi::IOC_WATCH_QUEUE_SET_SIZE as _,
+                queue_size as libc::c_ulong,
+            )
+        };
+        if res < 0 {
+            return Err(KeyError::from_errno());
+        }
+        Ok(watcher)
+    }
+
+    /// Watch a key for changes, reporting its events with the provided
+    /// watch ID.
+    ///
+    /// The key must grant the caller view permission. A key can only be
+    /// watched once per watcher.
+    pub fn watch_key(&self, key: Key, watch_id: u8) -> Result<(), KeyError> {
+        self.watch(key.get_id(), watch_id as libc::c_ulong)
+    }
+
+    /// Watch a keyring for changes, including keys being linked and
+    /// unlinked, reporting its events with the provided watch ID.
+    ///
+    /// The keyring must grant the caller view permission. A keyring can
+    /// only be watched once per watcher.
+    pub fn watch_keyring(&self, keyring: KeyRing, watch_id: u8) -> Result<(), KeyError> {
+        self.watch(keyring.get_id(), watch_id as libc::c_ulong)
+    }
+
+    /// Remove the watch on a key.
+    pub fn unwatch_key(&self, key: Key) -> Result<(), KeyError> {
+        self.watch(key.get_id(), -1i32 as libc::c_ulong)
+    }
+
+    /// Remove the watch on a keyring.
+    pub fn unwatch_keyring(&self, keyring: KeyRing) -> Result<(), KeyError> {
+        self.watch(keyring.get_id(), -1i32 as libc::c_ulong)
+    }
+
+    /// Read the next notification, blocking until one is available.
+    pub fn read(&mut self) -> Result<KeyNotification, KeyError> {
+        loop {
+            if let Some(notification) = self.next_pending() {
+                return Ok(notification);
+            }
+            self.fill()?;
+        }
+    }
+
+    /// Internal method to take the next decoded notification, if any
+    pub(crate) fn next_pending(&mut self) -> Option<KeyNotification> {
+        self.pending.pop_front()
+    }
+
+    /// Internal method to add or remove (watch ID of -1) a watch
+    fn watch(&self, id: KeySerialId, watch_id: libc::c_ulong) -> Result<(), KeyError> {
+        _ = ffi::keyctl!(
+            KeyCtlOperation::WatchKey,
+            id.as_raw_id() as libc::c_ulong,
+            self.fd as libc::c_ulong,
+            watch_id
+        )?;
+        Ok(())
+    }
+
+    /// Internal method to perform a single read from the pipe, queueing
+    /// every notification it returned.
+    pub(crate) fn fill(&mut self) -> Result<(), KeyError> {
+        let mut buffer = [0u8; 4096];
+        let len = loop {
+            let res = unsafe { libc::read(self.fd, buffer.as_mut_ptr() as _, buffer.len()) };
+            if res >= 0 {
+                break res as usize;
+            }
+            match KeyError::from_errno() {
+                KeyError::Unknown(libc::EINTR) => continue,
+                err => return Err(err),
+            }
+        };
+
+        // Split the data into records, each carries its own length
+        let mut data = &buffer[..len];
+        while data.len() >= HEADER_LEN {
+            let info = u32::from_ne_bytes([data[4], data[5], data[6], data[7]]);
+            let rlen = (info & ffi::WATCH_INFO_LENGTH) as usize;
+            if rlen < HEADER_LEN || rlen > data.len() {
+                break;
+            }
+            if let Some(notification) = KeyNotification::decode(&data[..rlen]) {
+                self.pending.push_back(notification);
+            }
+            data = &data[rlen..];
+        }
+        Ok(())
+    }
+}
+
+impl Drop for KeyWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+            libc::close(self.write_fd);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::os::fd::AsRawFd for KeyWatcher {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.fd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Vec;
+
+    /// Construct a raw notification record as the kernel would
+    fn record(wtype: u32, subtype: u8, watch_id: u8, words: &[u32]) -> Vec<u8> {
+        #[cfg(target_endian = "little")]
+        let header = wtype | ((subtype as u32) << 24);
+        #[cfg(target_endian = "big")]
+        let header = (wtype << 8) | subtype as u32;
+        let len = (HEADER_LEN + words.len() * 4) as u32;
+        let info = len | ((watch_id as u32) << ffi::WATCH_INFO_ID_SHIFT);
+
+        let mut raw = Vec::new();
+        raw.extend_from_slice(&header.to_ne_bytes());
+        raw.extend_from_slice(&info.to_ne_bytes());
+        for word in words {
+            raw.extend_from_slice(&word.to_ne_bytes());
+        }
+        raw
+    }
+
+    #[test]
+    fn test_decode_key_events() {
+        let id = KeySerialId::new(100);
+        let aux = KeySerialId::new(200);
+        for (subtype, expected) in [
+            (
+                KeyNotificationSubtype::Instantiated,
+                KeyEvent::Instantiated(id),
+            ),
+            (KeyNotificationSubtype::Updated, KeyEvent::Updated(id)),
+            (
+                KeyNotificationSubtype::Linked,
+                KeyEvent::Linked {
+                    keyring: id,
+                    key: aux,
+                },
+            ),
+            (
+                KeyNotificationSubtype::Unlinked,
+                KeyEvent::Unlinked {
+                    keyring: id,
+                    key: aux,
+                },
+            ),
+            (KeyNotificationSubtype::Cleared, KeyEvent::Cleared(id)),
+            (KeyNotificationSubtype::Revoked, KeyEvent::Revoked(id)),
+            (
+                KeyNotificationSubtype::Invalidated,
+                KeyEvent::Invalidated(id),
+            ),
+            (KeyNotificationSubtype::SetAttr, KeyEvent::SetAttr(id)),
+        ] {
+            let raw = record(ffi::WATCH_TYPE_KEY_NOTIFY, subtype as u8, 7, &[100, 200]);
+            let notification = KeyNotification::decode(&raw).unwrap();
+            assert_eq!(notification.get_watch_id(), 7);
+            assert_eq!(notification.get_event(), expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_meta_events() {
+        let raw = record(ffi::WATCH_TYPE_META, 0, 3, &[1, 0]);
+        let notification = KeyNotification::decode(&raw).unwrap();
+        assert_eq!(notification.get_watch_id(), 3);
+        assert_eq!(notification.get_event(), KeyEvent::WatchRemoved);
+
+        let raw = record(ffi::WATCH_TYPE_META, 1, 0, &[]);
+        let notification = KeyNotification::decode(&raw).unwrap();
+        assert_eq!(notification.get_event(), KeyEvent::Lost);
+    }
+
+    #[test]
+    fn test_decode_unknown() {
+        // Unknown types and subtypes are skipped
+        assert!(KeyNotification::decode(&record(5, 0, 0, &[1, 2])).is_none());
+        assert!(KeyNotification::decode(&record(ffi::WATCH_TYPE_META, 9, 0, &[])).is_none());
+        let raw = record(ffi::WATCH_TYPE_KEY_NOTIFY, 42, 0, &[1, 2]);
+        assert!(KeyNotification::decode(&raw).is_none());
+
+        // Truncated records are skipped
+        let raw = record(ffi::WATCH_TYPE_KEY_NOTIFY, 1, 0, &[1]);
+        assert!(KeyNotification::decode(&raw).is_none());
+        assert!(KeyNotification::decode(&[0u8; 4]).is_none());
+    }
+
+    #[test]
+    fn test_watch_keyring() {
+        KeyRing::with_new_session(|ring| {
+            // Skip kernels without notification support
+            let mut watcher = match KeyWatcher::new(16) {
+                Err(KeyError::OperationNotSupported) => return,
+                result => result.unwrap(),
+            };
+            match watcher.watch_keyring(ring, 1) {
+                Err(KeyError::OperationNotSupported) => return,
+                result => result.unwrap(),
+            }
+
+            // Linking a key is reported
+            let key = ring.add_key("test_watch_key", b"data").unwrap();
+            let notification = watcher.read().unwrap();
+            assert_eq!(notification.get_watch_id(), 1);
+            assert_eq!(
+                notification.get_event(),
+                KeyEvent::Linked {
+                    keyring: ring.get_id(),
+                    key: key.get_id(),
+                }
+            );
+
+            // As are changes to a watched key
+            watcher.watch_key(key, 2).unwrap();
+            key.update(b"rotated").unwrap();
+            let notification = watcher.read().unwrap();
+            assert_eq!(notification.get_watch_id(), 2);
+            assert_eq!(notification.get_event(), KeyEvent::Updated(key.get_id()));
+
+            // Watches can only be added once
+            let result = watcher.watch_key(key, 3);
+            assert!(result.is_err());
+            watcher.unwatch_key(key).unwrap();
+            watcher.unwatch_keyring(ring).unwrap();
+        })
+        .unwrap();
+    }
+}